@@ -0,0 +1,18 @@
+use std::path::PathBuf;
+
+/// Options of the assembler, usually given through the command line
+#[derive(Default)]
+pub struct Config {
+    /// If set, each instruction following data is moved to the next address aligned on 4 bytes
+    pub auto_align: bool,
+    /// The directories where the files given to .include are searched, after the directory of the including file
+    pub include_paths: Vec<PathBuf>,
+    /// The address where the program is loaded, the first section starts there
+    pub base: u64,
+    /// The label where the execution starts, it takes precedence over the .entry directive
+    pub entry: Option<String>,
+    /// The constants given with the option -D, declared before the first line
+    pub defines: Vec<(String, i32)>,
+    /// If set, the parsed lines are printed once the binary is generated
+    pub listing: bool,
+}
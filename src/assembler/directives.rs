@@ -0,0 +1,420 @@
+use super::{
+    errors::{SyntaxErrorKind, SyntaxResultKind},
+    word::{Word, WordContent},
+};
+
+/// Represent an assembler directive, a word starting with a dot that does not produce an instruction but drive the assembler
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Directive {
+    /// `.byte 1, 2, 'A'`: emit each value on one byte
+    Byte,
+    /// `.half 1, 2`: emit each value on two bytes
+    Half,
+    /// `.word label, 0xFFFF`: emit each value on four bytes
+    Word,
+    /// `.zero 64`: emit the given number of null bytes
+    Zero,
+    /// `.ascii "abc"`: emit the strings without any terminator
+    Ascii,
+    /// `.asciz "abc"`: emit the strings, each followed by a null byte
+    Asciz,
+    /// `.align 2[, fill]`: pad until the address is a multiple of 2 to the power of the given number
+    Align,
+    /// `.balign 4[, fill]`: pad until the address is a multiple of the given number of bytes
+    Balign,
+    /// `.section .data`: the following lines are placed in the given section
+    Section,
+    /// `.equ NAME, value`: declare a constant, it can't be declared again
+    Equ,
+    /// `.set NAME, value`: declare a constant or change its value for the following lines
+    Set,
+    /// `.enum [start]`: each name on the following lines is declared as a constant, with consecutive values
+    Enum,
+    /// `.endenum`: end the current enumeration
+    EndEnum,
+    /// `.include "io.nzm"`: assemble the lines of the given file at this place
+    Include,
+    /// `.once`: the current file is included at most one time
+    Once,
+    /// `.incbin "font.bin"[, offset[, length]]`: emit the bytes of the given file
+    Incbin,
+    /// `.org 0x2000`: move the location counter forward to the given absolute address, padding with null bytes
+    Org,
+    /// `.entry start`: the execution starts at the given label
+    Entry,
+    /// `.if DEBUG && LEVEL > 2`: the following lines are assembled only if the value is not null
+    If,
+    /// `.ifdef DEBUG`: the following lines are assembled only if the constant is declared
+    Ifdef,
+    /// `.ifndef DEBUG`: the following lines are assembled only if the constant is not declared
+    Ifndef,
+    /// `.else`: the following lines are assembled only if the previous condition was false
+    Else,
+    /// `.endif`: end the current conditional block
+    Endif,
+    /// `.struct Point`: the labels of the following lines are declared as constants holding their offset, such as Point.x
+    Struct,
+    /// `.ends`: end the current structure and declare its size, such as Point.size
+    Ends,
+    /// `.assert end - start <= 4096[, "message"]`: fail if the value is null, once the labels are laid out
+    Assert,
+    /// `.error "message"`: fail with the given message
+    Error,
+    /// `.warning "message"`: display the given message as a warning
+    Warning,
+    /// `.print "size:", end - start`: display the strings and the values, once the labels are laid out
+    Print,
+}
+
+/// Each section is laid out contiguously, in the order of the variants
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Section {
+    /// The code, it is the default section
+    #[default]
+    Text,
+    /// Read only data
+    Rodata,
+    /// Initialized data
+    Data,
+    /// Reserved space, no byte is emitted for this section
+    Bss,
+}
+
+impl Section {
+    pub const LAYOUT: [Section; 4] = [Self::Text, Self::Rodata, Self::Data, Self::Bss];
+}
+
+impl TryFrom<&str> for Section {
+    type Error = SyntaxErrorKind;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Ok(match s {
+            ".text" => Section::Text,
+            ".rodata" => Section::Rodata,
+            ".data" => Section::Data,
+            ".bss" => Section::Bss,
+            _ => return Err(SyntaxErrorKind::InvalidSection(s.to_string())),
+        })
+    }
+}
+
+/// The word used as fill value of an alignment directive to pad with no operation instructions instead of bytes
+pub const NOP_FILL: &str = "nop";
+
+impl TryFrom<&str> for Directive {
+    type Error = SyntaxErrorKind;
+
+    fn try_from(s: &str) -> Result<Self, SyntaxErrorKind> {
+        Ok(match s {
+            ".byte" => Directive::Byte,
+            ".half" => Directive::Half,
+            ".word" => Directive::Word,
+            ".zero" => Directive::Zero,
+            ".ascii" => Directive::Ascii,
+            ".asciz" => Directive::Asciz,
+            ".align" => Directive::Align,
+            ".balign" => Directive::Balign,
+            ".section" => Directive::Section,
+            ".equ" => Directive::Equ,
+            ".set" => Directive::Set,
+            ".enum" => Directive::Enum,
+            ".endenum" => Directive::EndEnum,
+            ".include" => Directive::Include,
+            ".once" => Directive::Once,
+            ".incbin" => Directive::Incbin,
+            ".org" => Directive::Org,
+            ".entry" => Directive::Entry,
+            ".if" => Directive::If,
+            ".ifdef" => Directive::Ifdef,
+            ".ifndef" => Directive::Ifndef,
+            ".else" => Directive::Else,
+            ".endif" => Directive::Endif,
+            ".struct" => Directive::Struct,
+            ".ends" => Directive::Ends,
+            ".assert" => Directive::Assert,
+            ".error" => Directive::Error,
+            ".warning" => Directive::Warning,
+            ".print" => Directive::Print,
+            _ => return Err(SyntaxErrorKind::InvalidDirective(s.to_string())),
+        })
+    }
+}
+
+impl Directive {
+    /// Returns the number of bytes used by a single numeric operand of the directive
+    pub fn item_size(self) -> usize {
+        match self {
+            Self::Byte => 1,
+            Self::Half => 2,
+            _ => 4,
+        }
+    }
+
+    /// Returns true if the given value can be stored on the directive item size, both signed and unsigned values are accepted
+    pub fn fits(self, value: i64) -> bool {
+        let bits = 8 * self.item_size() as u32;
+        value >= -(1 << (bits - 1)) && value < 1 << bits
+    }
+
+    pub fn check_compatibility(self, line: &[Word]) -> SyntaxResultKind<()> {
+        match self {
+            Self::Byte | Self::Half | Self::Word => {
+                if line.is_empty() || line.iter().any(|w| !w.is_imm()) {
+                    return Err(SyntaxErrorKind::ExpectedImmList(self));
+                }
+                if let Some(x) = line.iter().find_map(|w| match w.content {
+                    WordContent::Number(x) if !self.fits(x as i64) => Some(x),
+                    _ => None,
+                }) {
+                    return Err(SyntaxErrorKind::ValueOutOfRange(x as i64));
+                }
+                Ok(())
+            }
+            Self::Zero => match line {
+                [w] if matches!(w.content, WordContent::Number(x) if x >= 0) => Ok(()),
+                _ => Err(SyntaxErrorKind::ExpectedSize(self)),
+            },
+            Self::Ascii | Self::Asciz => {
+                if line.is_empty() || line.iter().any(|w| !w.is_str()) {
+                    Err(SyntaxErrorKind::ExpectedStrList(self))
+                } else {
+                    Ok(())
+                }
+            }
+            Self::Align | Self::Balign => {
+                let valid_boundary = match line.first().map(|w| &w.content) {
+                    Some(WordContent::Number(x)) if self == Self::Align => (0..32).contains(x),
+                    Some(WordContent::Number(x)) => *x > 0 && (*x as u32).is_power_of_two(),
+                    _ => false,
+                };
+                let valid_fill = match line.get(1).map(|w| &w.content) {
+                    None => true,
+                    Some(WordContent::Number(x)) => Self::Byte.fits(*x as i64),
+                    Some(WordContent::Label(lab)) => lab == NOP_FILL,
+                    _ => false,
+                };
+                if valid_boundary && valid_fill && line.len() <= 2 {
+                    Ok(())
+                } else {
+                    Err(SyntaxErrorKind::ExpectedAlignment(self))
+                }
+            }
+            Self::Section => match line {
+                [w] if w.get_section().is_some() => Ok(()),
+                [w] => match &w.content {
+                    WordContent::Label(lab) => Err(SyntaxErrorKind::InvalidSection(lab.clone())),
+                    _ => Err(SyntaxErrorKind::ExpectedSection),
+                },
+                _ => Err(SyntaxErrorKind::ExpectedSection),
+            },
+            Self::Equ | Self::Set => match line {
+                [name, value] if matches!(name.content, WordContent::Label(_)) => {
+                    match &value.content {
+                        WordContent::Number(_) => Ok(()),
+                        WordContent::Label(lab) => {
+                            Err(SyntaxErrorKind::ConstantNotResolved(lab.to_string()))
+                        }
+                        WordContent::Expr(expr) => Err(SyntaxErrorKind::ConstantNotResolved(
+                            expr.symbols()[0].to_string(),
+                        )),
+                        _ => Err(SyntaxErrorKind::ExpectedConstantDefinition(self)),
+                    }
+                }
+                _ => Err(SyntaxErrorKind::ExpectedConstantDefinition(self)),
+            },
+            Self::Enum => match line {
+                [] => Ok(()),
+                [w] if matches!(w.content, WordContent::Number(_)) => Ok(()),
+                _ => Err(SyntaxErrorKind::ExpectedNumberOrNothing(self)),
+            },
+            Self::EndEnum | Self::Once | Self::Else | Self::Endif | Self::Ends
+                if !line.is_empty() =>
+            {
+                Err(SyntaxErrorKind::ExpectedNoOperand(self))
+            }
+            Self::EndEnum | Self::Once | Self::Else | Self::Endif | Self::Ends => Ok(()),
+            Self::Include => match line {
+                [w] if w.is_str() => Ok(()),
+                _ => Err(SyntaxErrorKind::ExpectedFileName(self)),
+            },
+            Self::Incbin => match line {
+                [w, rest @ ..]
+                    if w.is_str()
+                        && rest.len() <= 2
+                        && rest
+                            .iter()
+                            .all(|w| matches!(w.content, WordContent::Number(x) if x >= 0)) =>
+                {
+                    Ok(())
+                }
+                _ => Err(SyntaxErrorKind::ExpectedFileSlice(self)),
+            },
+            Self::Org => match line {
+                [w] if matches!(w.content, WordContent::Number(x) if x >= 0) => Ok(()),
+                _ => Err(SyntaxErrorKind::ExpectedAddress(self)),
+            },
+            Self::Entry => match line {
+                [w] if matches!(w.content, WordContent::Label(_)) => Ok(()),
+                _ => Err(SyntaxErrorKind::ExpectedLabel(self)),
+            },
+            Self::If => match line {
+                [w] => match &w.content {
+                    WordContent::Number(_) => Ok(()),
+                    WordContent::Label(lab) => {
+                        Err(SyntaxErrorKind::ConstantNotResolved(lab.to_string()))
+                    }
+                    WordContent::Expr(expr) => Err(SyntaxErrorKind::ConstantNotResolved(
+                        expr.symbols()[0].to_string(),
+                    )),
+                    _ => Err(SyntaxErrorKind::ExpectedCondition(self)),
+                },
+                _ => Err(SyntaxErrorKind::ExpectedCondition(self)),
+            },
+            Self::Assert => match line {
+                [value] | [value, _]
+                    if value.is_imm() && line.get(1).is_none_or(|w| w.is_str()) =>
+                {
+                    Ok(())
+                }
+                _ => Err(SyntaxErrorKind::ExpectedAssertion(self)),
+            },
+            Self::Error | Self::Warning => match line {
+                [w] if w.is_str() => Ok(()),
+                _ => Err(SyntaxErrorKind::ExpectedMessage(self)),
+            },
+            Self::Print => {
+                if line.is_empty() || line.iter().any(|w| !w.is_str() && !w.is_imm()) {
+                    Err(SyntaxErrorKind::ExpectedPrintList(self))
+                } else {
+                    Ok(())
+                }
+            }
+            Self::Ifdef | Self::Ifndef | Self::Struct => match line {
+                [w] if matches!(w.content, WordContent::Label(_)) => Ok(()),
+                _ => Err(SyntaxErrorKind::ExpectedName(self)),
+            },
+        }
+    }
+
+    /// Returns the number of operands at the beginning of the line that are names, and so that must not be replaced by the value of a constant
+    pub fn name_operands(self) -> usize {
+        match self {
+            Self::Equ | Self::Set | Self::Ifdef | Self::Ifndef | Self::Struct => 1,
+            _ => 0,
+        }
+    }
+
+    /// Returns true if the directive opens a conditional block
+    pub fn is_condition(self) -> bool {
+        matches!(self, Self::If | Self::Ifdef | Self::Ifndef)
+    }
+
+    /// Returns true if the directive is evaluated once the labels are laid out, to check the program or to display something
+    pub fn is_diagnostic(self) -> bool {
+        matches!(
+            self,
+            Self::Assert | Self::Error | Self::Warning | Self::Print
+        )
+    }
+
+    /// Returns true if the directive puts bytes in the binary, the other ones only drive the assembler
+    pub fn emits_bytes(self) -> bool {
+        match self {
+            Self::Byte
+            | Self::Half
+            | Self::Word
+            | Self::Zero
+            | Self::Ascii
+            | Self::Asciz
+            | Self::Align
+            | Self::Balign
+            | Self::Incbin => true,
+            Self::Section
+            | Self::Equ
+            | Self::Set
+            | Self::Enum
+            | Self::EndEnum
+            | Self::Include
+            | Self::Once
+            | Self::Org
+            | Self::Entry
+            | Self::If
+            | Self::Ifdef
+            | Self::Ifndef
+            | Self::Else
+            | Self::Endif
+            | Self::Struct
+            | Self::Ends
+            | Self::Assert
+            | Self::Error
+            | Self::Warning
+            | Self::Print => false,
+        }
+    }
+
+    /// Returns true if the directive can be used in the bss section, meaning that it does not emit anything else than null bytes
+    pub fn is_reservation(self) -> bool {
+        !self.emits_bytes() || matches!(self, Self::Zero | Self::Align | Self::Balign)
+    }
+
+    /// Returns the boundary in bytes of an alignment directive, assuming the operands have been checked
+    pub fn alignment(self, line: &[&Word]) -> u64 {
+        let x = match line[0].content {
+            WordContent::Number(x) => x as u64,
+            _ => unreachable!(),
+        };
+        match self {
+            Self::Align => 1 << x,
+            _ => x,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_parsed() {
+        assert_eq!(Directive::try_from(".byte").unwrap(), Directive::Byte);
+        assert_eq!(Directive::try_from(".endenum").unwrap(), Directive::EndEnum);
+        assert!(matches!(
+            Directive::try_from(".bytes"),
+            Err(SyntaxErrorKind::InvalidDirective(_))
+        ));
+        assert_eq!(Section::try_from(".bss").unwrap(), Section::Bss);
+        assert!(Section::try_from(".code").is_err());
+    }
+
+    #[test]
+    fn items_fit_as_signed_or_unsigned() {
+        assert!(Directive::Byte.fits(255));
+        assert!(Directive::Byte.fits(-128));
+        assert!(!Directive::Byte.fits(256));
+        assert!(!Directive::Byte.fits(-129));
+        assert!(Directive::Half.fits(0xFFFF));
+        assert!(!Directive::Half.fits(0x10000));
+        assert!(Directive::Word.fits(0xFFFF_FFFF));
+        assert!(Directive::Word.fits(-0x8000_0000));
+        assert!(!Directive::Word.fits(0x1_0000_0000));
+    }
+
+    #[test]
+    fn reservations_only_emit_null_bytes() {
+        for directive in [Directive::Byte, Directive::Ascii, Directive::Incbin] {
+            assert!(!directive.is_reservation());
+        }
+        for directive in [Directive::Zero, Directive::Balign, Directive::Print] {
+            assert!(directive.is_reservation());
+        }
+        // The directives emitting no byte are accepted in the bss section
+        for directive in [
+            Directive::Equ,
+            Directive::Set,
+            Directive::Enum,
+            Directive::EndEnum,
+        ] {
+            assert!(!directive.emits_bytes() && directive.is_reservation());
+        }
+    }
+}
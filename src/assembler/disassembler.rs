@@ -0,0 +1,329 @@
+use std::{fs, path::Path};
+
+use super::{
+    isa::{Code, Form, Layout, Operand, ISA},
+    op_codes::{
+        OpCode, ABSOLUTE_BASE, ALU_SHIFT, COND_DST_SHIFT, DST_SHIFT, EXTENDED, EXT_SHIFT,
+        JCODE_SHIFT, OFFSET_SHIFT, SET_BIT, SRC_SHIFT, THREE_OPERANDS, WIDTH_SHIFT,
+    },
+    registers::{Register, NAMES},
+    syscalls::find_syscall,
+};
+
+/// Returns the bits of the field of the given size starting at the given bit
+fn field(instr: u32, shift: u32, bits: u32) -> u32 {
+    instr >> shift & ((1 << bits) - 1)
+}
+
+/// Returns the value of the field sign extended
+fn signed_field(instr: u32, shift: u32, bits: u32) -> i64 {
+    let value = field(instr, shift, bits) as i64;
+    if value >= 1 << (bits - 1) {
+        value - (1 << bits)
+    } else {
+        value
+    }
+}
+
+/// Returns the name of the register stored on 4 bits from the given bit
+fn reg(instr: u32, shift: u32) -> Option<String> {
+    NAMES
+        .get(field(instr, shift, 4) as usize)
+        .map(|name| name.to_string())
+}
+
+/// Returns the register or the immediate value stored under the flag, the immediate value of an interrupt is displayed by its name. The bits between a register and the flag must be null.
+fn reg_or_imm(instr: u32, flag_shift: u32, interrupt: bool) -> Option<String> {
+    if instr >> flag_shift & 1 == 0 {
+        (field(instr, 4, flag_shift - 4) == 0).then_some(())?;
+        return reg(instr, 0);
+    }
+    let value = signed_field(instr, 0, flag_shift);
+    Some(match find_syscall(value).filter(|_| interrupt) {
+        Some(syscall) => syscall.name.to_string(),
+        None => value.to_string(),
+    })
+}
+
+/// Returns the memory operand of a base register with a signed offset
+fn memory(base: String, offset: i64) -> String {
+    match offset {
+        0 => format!("[{base}]"),
+        offset if offset < 0 => format!("[{base} - {}]", -offset),
+        offset => format!("[{base} + {offset}]"),
+    }
+}
+
+/// Decode the operands of a load, the destination followed by the address read
+fn load(instr: u32, flag_shift: u32) -> Option<Vec<String>> {
+    let address = if instr >> flag_shift & 1 == 1 {
+        format!("[{}]", field(instr, 0, flag_shift))
+    } else {
+        memory(
+            reg(instr, 0)?,
+            signed_field(instr, OFFSET_SHIFT, flag_shift - OFFSET_SHIFT),
+        )
+    };
+    Some(vec![reg(instr, DST_SHIFT)?, address])
+}
+
+/// Decode the operands of a store, the address written followed by the value
+fn store(instr: u32, flag_shift: u32) -> Option<Vec<String>> {
+    let offset_bits = flag_shift - OFFSET_SHIFT;
+    Some(if instr >> flag_shift & 1 == 1 {
+        vec![
+            format!("[{}]", reg(instr, DST_SHIFT)?),
+            reg_or_imm(instr, flag_shift, false)?,
+        ]
+    } else if field(instr, DST_SHIFT, 4) == ABSOLUTE_BASE as u32 {
+        vec![
+            format!("[{}]", field(instr, OFFSET_SHIFT, offset_bits)),
+            reg(instr, 0)?,
+        ]
+    } else {
+        vec![
+            memory(
+                reg(instr, DST_SHIFT)?,
+                signed_field(instr, OFFSET_SHIFT, offset_bits),
+            ),
+            reg(instr, 0)?,
+        ]
+    })
+}
+
+/// Decode the operands of the instruction laid out by the form, None if the bits do not fit it
+fn operands(instr: u32, form: &Form, interrupt: bool) -> Option<Vec<String>> {
+    let shift = form.code.bits().1;
+    // The bits under the code of the instruction
+    let payload = field(instr, 0, 27 - shift);
+    Some(match form.layout {
+        Layout::Nothing => (payload == 0).then(Vec::new)?,
+        // A single register operand must not have its flag set
+        Layout::Single if form.operands[0] != Operand::RegOrImm => {
+            (field(instr, 4, 23 - shift) == 0).then_some(())?;
+            vec![reg(instr, 0)?]
+        }
+        Layout::Single => vec![reg_or_imm(instr, 26 - shift, interrupt)?],
+        Layout::Jump => vec![reg_or_imm(instr, JCODE_SHIFT - 1, false)?],
+        Layout::Double => vec![
+            reg(instr, DST_SHIFT - shift)?,
+            reg_or_imm(instr, DST_SHIFT - 1 - shift, false)?,
+        ],
+        Layout::Triple => vec![
+            reg(instr, DST_SHIFT)?,
+            reg(instr, SRC_SHIFT)?,
+            reg_or_imm(instr, ALU_SHIFT - 1, false)?,
+        ],
+        Layout::Load => load(instr, DST_SHIFT - 1)?,
+        Layout::Store => store(instr, DST_SHIFT - 1)?,
+        Layout::SizedLoad => load(instr, WIDTH_SHIFT - 1)?,
+        Layout::SizedStore => store(instr, WIDTH_SHIFT - 1)?,
+        Layout::CondMove => {
+            (instr >> SET_BIT & 1 == 0).then_some(())?;
+            vec![
+                reg(instr, COND_DST_SHIFT)?,
+                reg_or_imm(instr, SET_BIT - 1, false)?,
+            ]
+        }
+        Layout::CondSet => {
+            (instr >> SET_BIT & 1 == 1 && field(instr, 0, SET_BIT) == 0).then_some(())?;
+            vec![reg(instr, COND_DST_SHIFT)?]
+        }
+        Layout::Mask => {
+            (payload != 0 && payload < 1 << NAMES.len()).then_some(())?;
+            (0..NAMES.len())
+                .filter(|n| payload >> n & 1 == 1)
+                .map(|n| NAMES[n].to_string())
+                .collect()
+        }
+        // The shorthands are displayed in their complete form
+        Layout::Once | Layout::InPlace => return None,
+    })
+}
+
+/// Returns true if the registers written by the instruction are not read only, as the assembler refuses the other ones
+fn writable(form: &Form, operands: &[String]) -> bool {
+    operands
+        .iter()
+        // The single operand of a list of registers matches each of them
+        .zip(form.operands.iter().cycle())
+        .filter(|(_, op)| matches!(op, Operand::Dst | Operand::Registers))
+        .all(|(name, _)| Register::try_from(name.as_str()).is_ok_and(|reg| reg.is_writable()))
+}
+
+/// Returns the condition or the width stored with the instruction, if its layout has one
+fn parameter(instr: u32, layout: Layout) -> Option<u8> {
+    match layout {
+        Layout::Jump | Layout::CondMove | Layout::CondSet => {
+            Some(field(instr, JCODE_SHIFT, 4) as u8)
+        }
+        Layout::SizedLoad | Layout::SizedStore => Some(field(instr, WIDTH_SHIFT, 2) as u8),
+        _ => None,
+    }
+}
+
+/// Returns the assembly of an instruction, None if the word is not a valid instruction
+pub fn disassemble(instr: u32) -> Option<String> {
+    let code = match (instr >> 27) as u8 {
+        EXTENDED => Code::Ext(field(instr, EXT_SHIFT, 6) as u8),
+        THREE_OPERANDS => Code::Alu(field(instr, ALU_SHIFT, 4) as u8),
+        n => Code::Op(n),
+    };
+    ISA.iter().find_map(|instruction| {
+        instruction
+            .forms
+            .iter()
+            .filter(|form| form.code == code)
+            .find_map(|form| {
+                let parameter = parameter(instr, form.layout);
+                let (mnemonic, op_code) = instruction
+                    .mnemonics
+                    .iter()
+                    .find(|(_, op_code)| op_code.parameter() == parameter)?;
+                let operands = operands(instr, form, *op_code == OpCode::INT)?;
+                writable(form, &operands).then_some(())?;
+                Some(match operands.is_empty() {
+                    true => mnemonic.to_string(),
+                    false => format!("{mnemonic} {}", operands.join(", ")),
+                })
+            })
+    })
+}
+
+/// Print the header and the instructions of a binary produced by the assembler, as given by the option --disassemble. The code is loaded at the base address.
+pub fn print_disassembly(path: &Path, base: u64) {
+    let binary = fs::read(path).expect("Failed to read the binary file");
+    if binary.len() < 8 {
+        println!("The file is too short to hold the header");
+        return;
+    }
+    let word = |i: usize| u32::from_be_bytes(binary[i..i + 4].try_into().unwrap());
+    println!("version {}, entry {:#010x}", word(0), word(4));
+    for (i, chunk) in binary[8..].chunks(4).enumerate() {
+        let address = base + 4 * i as u64;
+        match chunk.try_into().map(u32::from_be_bytes) {
+            Ok(instr) => match disassemble(instr) {
+                Some(text) => println!("{address:#010x}  {text}"),
+                None => println!("{address:#010x}  .word {instr:#010x}"),
+            },
+            Err(_) => println!(
+                "{address:#010x}  .byte {}",
+                chunk
+                    .iter()
+                    .map(|b| format!("{b:#04x}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::tests::{try_assemble, words};
+
+    /// Operands of each kind given to the forms of the table, an address is written between brackets as the disassembler displays it
+    fn samples(operand: Operand) -> &'static [&'static str] {
+        match operand {
+            Operand::Dst => &["r3", "rpc"],
+            Operand::Reg => &["r5", "rcond"],
+            Operand::RegOrImm => &["r2", "-7", "300"],
+            Operand::Address => &["[r4 + 8]", "[r4 - 4]", "[r4]", "[64]"],
+            Operand::Target => &["[r2 + 4]", "[r6]", "[64]"],
+            Operand::Registers => &["r0, r3, rpc"],
+        }
+    }
+
+    /// Returns the lines combining the samples of each operand of the form
+    fn lines(mnemonic: &str, form: &Form) -> Vec<String> {
+        form.operands
+            .iter()
+            .fold(vec![Vec::new()], |lines, op| {
+                lines
+                    .iter()
+                    .flat_map(|line| {
+                        samples(*op)
+                            .iter()
+                            .map(move |s| [line.clone(), vec![*s]].concat())
+                    })
+                    .collect()
+            })
+            .into_iter()
+            .map(|ops| {
+                format!("{mnemonic} {}", ops.join(", "))
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_form_is_disassembled_back() {
+        for instruction in ISA {
+            for (mnemonic, _) in instruction.mnemonics {
+                for form in instruction.forms {
+                    let mut tested = 0;
+                    for line in lines(mnemonic, form) {
+                        // Some combinations are refused, such as an immediate value written with an offset
+                        let Ok(binary) = try_assemble(&line) else {
+                            continue;
+                        };
+                        let word = u32::from_be_bytes(binary[..4].try_into().unwrap());
+                        let text =
+                            disassemble(word).unwrap_or_else(|| panic!("{line}: {word:#010x}"));
+                        // The shorthands are displayed in their complete form
+                        if !form.layout.is_shorthand() {
+                            assert_eq!(text, line);
+                        }
+                        assert_eq!(words(&text), [word], "{line}");
+                        tested += 1;
+                    }
+                    assert!(tested > 0, "{mnemonic} {:?}", form.operands);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_words_are_not_disassembled() {
+        // neg without operand with bits set, clear without register, a free extended code
+        for word in [0x28000001, 0xF9C00000, 0xF9E00000] {
+            assert_eq!(disassemble(word), None, "{word:#010x}");
+        }
+        // A bit set between the register and the flag, in jmp r1, mov r1, r2, add r2, r0, r1, cmovl r0, r1 and rol r1, r3
+        for word in [
+            12 << 27 | 1 << 10 | 1,
+            18 << 27 | 1 << 23 | 1 << 21 | 2,
+            29 << 27 | 2 << 23 | 1 << 4 | 1,
+            30 << 27 | 3 << 23 | 1 << 16 | 1,
+            31 << 27 | 4 << 21 | 1 << 17 | 1 << 15 | 3,
+        ] {
+            assert_eq!(disassemble(word), None, "{word:#010x}");
+        }
+    }
+
+    #[test]
+    fn disassembled_words_are_assembled_back() {
+        // A sweep of words spread over all the op codes, each one displayed must give back the same word
+        let mut word: u32 = 1;
+        for _ in 0..20000 {
+            word = word.wrapping_mul(1664525).wrapping_add(1013904223);
+            // Keep the high bits so each op code is reached, and few low bits so the registers are valid
+            let candidate = word & 0xFFE0_0000 | word >> 7 & 0x0F;
+            for instr in [word, candidate] {
+                if let Some(text) = disassemble(instr) {
+                    let binary = try_assemble(&text)
+                        .unwrap_or_else(|e| panic!("{instr:#010x}: {text}: {e:?}"));
+                    assert_eq!(binary, instr.to_be_bytes(), "{instr:#010x}: {text}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn interrupts_are_displayed_by_name() {
+        assert_eq!(disassemble(words("int 1")[0]).unwrap(), "int SYS_WRITE");
+        assert_eq!(disassemble(words("int 99")[0]).unwrap(), "int 99");
+    }
+}
@@ -1,11 +1,14 @@
 use std::fmt::{Debug, Display, Error as FmtErr, Formatter};
 
-use super::op_codes::OpCode;
+use super::{
+    directives::Directive, expr::OPERATOR_CHARS, location::Location, op_codes::OpCode,
+    registers::Register,
+};
 
 pub enum SyntaxErrorKind {
     SyntaxError,
     EmptyText,
-    NoMain,
+    EntryNotFound(String),
     InvalidSingleQuote(String),
     InvalidBackSlash(char),
     BackSlashNeeded(char),
@@ -24,6 +27,69 @@ pub enum SyntaxErrorKind {
     ExpectedReg(OpCode),
     ExpectedRegOrImm(OpCode),
     ExpectedNothing(OpCode),
+    InvalidDirective(String),
+    ExpectedImmList(Directive),
+    ExpectedStrList(Directive),
+    ExpectedSize(Directive),
+    ValueOutOfRange(i64),
+    ExpectedAlignment(Directive),
+    InvalidSection(String),
+    ExpectedSection,
+    InitializedDataInBss,
+    InvalidExpression(String),
+    DivisionByZero,
+    ConstantDeclaredTwice(String),
+    ConstantNotResolved(String),
+    ExpectedConstantDefinition(Directive),
+    ExpectedConstantName(String),
+    EnumNeverEnded,
+    UnexpectedEndEnum,
+    ExpectedNumberOrNothing(Directive),
+    ExpectedNoOperand(Directive),
+    CantReadFile(String),
+    IncludeNotFound(String),
+    IncludeCycle(String),
+    ExpectedFileName(Directive),
+    ExpectedFileSlice(Directive),
+    FileTooShort(String, usize),
+    ExpectedAddress(Directive),
+    OrgBelowBase(u64, u64),
+    OrgOverlap(u64, u64),
+    ExpectedLabel(Directive),
+    EntryDeclaredTwice,
+    ExpectedCondition(Directive),
+    ExpectedName(Directive),
+    UnexpectedElse,
+    UnexpectedEndif,
+    IfNeverEnded,
+    MacroNeverEnded,
+    UnexpectedEndm,
+    MacroDeclaredTwice(String),
+    InvalidMacroName(String),
+    InvalidMacroParameter(String),
+    MissingArgument(String, String),
+    TooManyArguments(String),
+    MacroTooDeep(String),
+    ExpectedRepetitionCount,
+    UnexpectedEndr,
+    RepetitionNeverEnded,
+    StructNeverEnded,
+    UnexpectedEnds,
+    UnexpectedStruct,
+    ExpectedField,
+    ExpectedAssertion(Directive),
+    ExpectedMessage(Directive),
+    ExpectedPrintList(Directive),
+    AssertionFailed(Option<String>),
+    UserError(String),
+    InvalidPseudoOperands(&'static str),
+    ReadOnlyRegister(Register),
+    InvalidMemoryOperand(String),
+    ExpectedMemoryAccess(OpCode),
+    ImmediateWithOffset,
+    ExpectedThreeOperands(OpCode),
+    ExpectedRegList(OpCode),
+    ReservedName(String),
 }
 
 impl Debug for SyntaxErrorKind {
@@ -34,7 +100,7 @@ impl Debug for SyntaxErrorKind {
             match self {
                 Self::SyntaxError => String::from("Syntax error"),
                 Self::EmptyText => String::from("The file is empty."),
-                Self::NoMain => String::from("Main label has not been found."),
+                Self::EntryNotFound(lab) => format!("The entry label \"{lab}\" has not been found."),
                 Self::InvalidSingleQuote(pure_content) =>
                     format!("The quote is invalid. \"{pure_content}\" has been found."),
                 Self::InvalidBackSlash(c) => format!("The char '{c}' has been found after a backslash, this char is not allowed as a backslash char."),
@@ -53,6 +119,70 @@ impl Debug for SyntaxErrorKind {
                 Self::ExpectedReg(op) => format!("With the op code {op:?}, a single register was expected."),
                 Self::ExpectedRegOrImm(op) => format!("With the op code {op:?}, a register or an immediate value was expected."),
                 Self::ExpectedNothing(op) => format!("We expected nothing after the op code {op:?}."),
+                Self::InvalidDirective(dir) => format!("The directive \"{dir}\" does not exist."),
+                Self::ExpectedImmList(dir) => format!("With the directive {dir:?}, a list of numbers or labels separated by commas was expected."),
+                Self::ExpectedStrList(dir) => format!("With the directive {dir:?}, a list of strings separated by commas was expected."),
+                Self::ExpectedSize(dir) => format!("With the directive {dir:?}, a single positive number was expected."),
+                Self::ValueOutOfRange(x) => format!("The value {x} is too large to be stored here."),
+                Self::InvalidSection(section) => format!("The section \"{section}\" does not exist, expected .text, .rodata, .data or .bss."),
+                Self::ExpectedSection => String::from("With the directive Section, a single section name was expected."),
+                Self::InitializedDataInBss => String::from("The bss section can only contain reservations (.zero, .align and .balign)."),
+                Self::InvalidExpression(expr) => format!("The expression \"{expr}\" is invalid."),
+                Self::DivisionByZero => String::from("Division by zero in an expression."),
+                Self::ConstantDeclaredTwice(name) => format!("The constant \"{name}\" as been declared twice, use .set to change its value."),
+                Self::ConstantNotResolved(name) => format!("The value of a constant can only use numbers and constants declared before, \"{name}\" is unknown."),
+                Self::ExpectedConstantDefinition(dir) => format!("With the directive {dir:?}, a name followed with a value was expected."),
+                Self::ExpectedConstantName(word) => format!("Expected the name of a constant, found \"{word}\"."),
+                Self::EnumNeverEnded => String::from("An .enum block has never been ended by .endenum."),
+                Self::UnexpectedEndEnum => String::from("Found .endenum outside of an .enum block."),
+                Self::ExpectedNumberOrNothing(dir) => format!("With the directive {dir:?}, a single number or nothing was expected."),
+                Self::ExpectedNoOperand(dir) => format!("We expected nothing after the directive {dir:?}."),
+                Self::CantReadFile(path) => format!("The file \"{path}\" can't be read."),
+                Self::IncludeNotFound(path) => format!("The file \"{path}\" has not been found next to the current file nor in the include paths."),
+                Self::IncludeCycle(path) => format!("The file \"{path}\" includes itself."),
+                Self::ExpectedFileName(dir) => format!("With the directive {dir:?}, a file name between double quotes was expected."),
+                Self::ExpectedFileSlice(dir) => format!("With the directive {dir:?}, a file name between double quotes eventually followed with a positive offset and a positive length was expected."),
+                Self::FileTooShort(path, len) => format!("The file \"{path}\" only contains {len} bytes."),
+                Self::ExpectedAddress(dir) => format!("With the directive {dir:?}, a positive address was expected."),
+                Self::OrgBelowBase(target, base) => format!("Can't move the location counter backwards to {target:#x}, the program starts at {base:#x}."),
+                Self::OrgOverlap(target, addr) => format!("Can't move the location counter to {target:#x}, the previous lines already go up to {addr:#x}."),
+                Self::ExpectedLabel(dir) => format!("With the directive {dir:?}, a label was expected."),
+                Self::EntryDeclaredTwice => String::from("The entry point has already been declared."),
+                Self::ExpectedCondition(dir) => format!("With the directive {dir:?}, a number or an expression of constants was expected."),
+                Self::ExpectedName(dir) => format!("With the directive {dir:?}, the name of a constant was expected."),
+                Self::UnexpectedElse => String::from("Found .else outside of a conditional block, or after an other .else."),
+                Self::UnexpectedEndif => String::from("Found .endif outside of a conditional block."),
+                Self::IfNeverEnded => String::from("This conditional block has never been ended by .endif."),
+                Self::MacroNeverEnded => String::from("This macro has never been ended by .endm."),
+                Self::UnexpectedEndm => String::from("Found .endm outside of a macro definition."),
+                Self::MacroDeclaredTwice(name) => format!("The macro \"{name}\" as been declared twice."),
+                Self::InvalidMacroName(name) => format!("\"{name}\" can't be the name of a macro."),
+                Self::InvalidMacroParameter(param) => format!("Invalid macro parameter: \"{param}\", only the last parameter can be variadic and each name has to be unique."),
+                Self::MissingArgument(name, param) => format!("The macro \"{name}\" expects an argument for the parameter \"{param}\"."),
+                Self::TooManyArguments(name) => format!("Too many arguments for the macro \"{name}\"."),
+                Self::MacroTooDeep(name) => format!("Too many nested expansions when expanding the macro \"{name}\", it probably invokes itself endlessly."),
+                Self::ExpectedRepetitionCount => String::from("A positive number of iterations was expected after .rept."),
+                Self::UnexpectedEndr => String::from("Found .endr outside of a .rept or an .irp block."),
+                Self::RepetitionNeverEnded => String::from("This block has never been ended by .endr."),
+                Self::StructNeverEnded => String::from("This structure has never been ended by .ends."),
+                Self::UnexpectedEnds => String::from("Found .ends outside of a .struct block."),
+                Self::UnexpectedStruct => String::from("A .struct block can't be declared inside an other one."),
+                Self::ExpectedField => String::from("Only labels and data directives are expected inside a .struct block."),
+                Self::ExpectedAssertion(dir) => format!("With the directive {dir:?}, a value eventually followed with a message between double quotes was expected."),
+                Self::ExpectedMessage(dir) => format!("With the directive {dir:?}, a message between double quotes was expected."),
+                Self::ExpectedPrintList(dir) => format!("With the directive {dir:?}, a list of strings and values separated by commas was expected."),
+                Self::AssertionFailed(Some(msg)) => format!("Assertion failed: {msg}"),
+                Self::AssertionFailed(None) => String::from("Assertion failed."),
+                Self::UserError(msg) => msg.to_string(),
+                Self::InvalidPseudoOperands(usage) => format!("Invalid operands for the pseudo-instruction, expected: {usage}"),
+                Self::ReadOnlyRegister(reg) => format!("The register {reg:?} is read only, it can't be used as a destination."),
+                Self::InvalidMemoryOperand(s) => format!("The memory operand \"{s}\" is invalid, a register eventually followed with an offset, or an address, was expected between brackets."),
+                Self::ExpectedMemoryAccess(op) => format!("With the op code {op:?}, a register and a memory operand, a register or an immediate value were expected."),
+                Self::ImmediateWithOffset => String::from("An immediate value can only be written at the address held by a register, without offset."),
+                Self::ExpectedRegList(op) => format!("With the op code {op:?}, nothing or a list of registers separated by commas was expected."),
+                Self::ExpectedThreeOperands(op) => format!("With the op code {op:?}, a destination register followed with a register and an other register or an immediate value was expected."),
+                Self::ReservedName(name) => format!("The name {name} is the one of an interrupt of the ZVM, it can't be declared as a label or a constant."),
+                Self::ExpectedAlignment(dir) => format!("With the directive {dir:?}, a valid alignment eventually followed by a byte or nop as fill value was expected."),
             }
         )
     }
@@ -64,9 +194,43 @@ impl Display for SyntaxErrorKind {
     }
 }
 
+/// Write the header of a diagnostic, followed by the message and the lines that brought it, .include lines and macro invocations, if it is located
+fn write_diagnostic(
+    f: &mut Formatter,
+    header: &str,
+    line: usize,
+    location: &Option<Location>,
+    message: &dyn Debug,
+) -> core::result::Result<(), FmtErr> {
+    match location {
+        Some(location) => {
+            write!(f, "{header} in {location:?}, {message:?}")?;
+            for l in location.chain() {
+                if let Some(parent) = &l.parent {
+                    write!(f, "\n    {:?} {parent:?}", l.origin)?;
+                }
+            }
+            Ok(())
+        }
+        None => write!(f, "{header} line {line}, {message:?}"),
+    }
+}
+
 pub struct SyntaxError {
     line: usize,
     err: SyntaxErrorKind,
+    /// The position in the sources of the line, known once the error reached the assembler
+    location: Option<Location>,
+}
+
+impl SyntaxError {
+    /// Attach the location in the sources of the line where the error happened, the lines are counted from 1 and the line 0 concerns the whole program
+    pub fn locate(mut self, locations: &[Location]) -> Self {
+        if self.line > 0 {
+            self.location = locations.get(self.line - 1).cloned();
+        }
+        self
+    }
 }
 
 impl From<&SyntaxError> for i32 {
@@ -77,7 +241,7 @@ impl From<&SyntaxError> for i32 {
 
 impl Debug for SyntaxError {
     fn fmt(&self, f: &mut Formatter) -> core::result::Result<(), FmtErr> {
-        write!(f, "Error line {}, {:?}", self.line, self.err)
+        write_diagnostic(f, "Error", self.line, &self.location, &self.err)
     }
 }
 
@@ -110,8 +274,122 @@ impl From<&SyntaxErrorKind> for i32 {
             SyntaxErrorKind::ExpectedReg(_) => 18,
             SyntaxErrorKind::ExpectedRegOrImm(_) => 19,
             SyntaxErrorKind::ExpectedNothing(_) => 20,
-            SyntaxErrorKind::NoMain => 21,
+            SyntaxErrorKind::EntryNotFound(_) => 21,
+            SyntaxErrorKind::InvalidDirective(_) => 22,
+            SyntaxErrorKind::ExpectedImmList(_) => 23,
+            SyntaxErrorKind::ExpectedStrList(_) => 24,
+            SyntaxErrorKind::ExpectedSize(_) => 25,
+            SyntaxErrorKind::ValueOutOfRange(_) => 26,
+            SyntaxErrorKind::ExpectedAlignment(_) => 27,
+            SyntaxErrorKind::InvalidSection(_) => 28,
+            SyntaxErrorKind::ExpectedSection => 29,
+            SyntaxErrorKind::InitializedDataInBss => 30,
+            SyntaxErrorKind::InvalidExpression(_) => 31,
+            SyntaxErrorKind::DivisionByZero => 32,
+            SyntaxErrorKind::ConstantDeclaredTwice(_) => 33,
+            SyntaxErrorKind::ConstantNotResolved(_) => 34,
+            SyntaxErrorKind::ExpectedConstantDefinition(_) => 35,
+            SyntaxErrorKind::ExpectedConstantName(_) => 36,
+            SyntaxErrorKind::EnumNeverEnded => 37,
+            SyntaxErrorKind::UnexpectedEndEnum => 38,
+            SyntaxErrorKind::ExpectedNumberOrNothing(_) => 39,
+            SyntaxErrorKind::ExpectedNoOperand(_) => 40,
+            SyntaxErrorKind::CantReadFile(_) => 41,
+            SyntaxErrorKind::IncludeNotFound(_) => 42,
+            SyntaxErrorKind::IncludeCycle(_) => 43,
+            SyntaxErrorKind::ExpectedFileName(_) => 44,
+            SyntaxErrorKind::ExpectedFileSlice(_) => 45,
+            SyntaxErrorKind::FileTooShort(_, _) => 46,
+            SyntaxErrorKind::ExpectedAddress(_) => 47,
+            SyntaxErrorKind::OrgBelowBase(_, _) => 48,
+            SyntaxErrorKind::OrgOverlap(_, _) => 49,
+            SyntaxErrorKind::ExpectedLabel(_) => 50,
+            SyntaxErrorKind::EntryDeclaredTwice => 51,
+            SyntaxErrorKind::ExpectedCondition(_) => 52,
+            SyntaxErrorKind::ExpectedName(_) => 53,
+            SyntaxErrorKind::UnexpectedElse => 54,
+            SyntaxErrorKind::UnexpectedEndif => 55,
+            SyntaxErrorKind::IfNeverEnded => 56,
+            SyntaxErrorKind::MacroNeverEnded => 57,
+            SyntaxErrorKind::UnexpectedEndm => 58,
+            SyntaxErrorKind::MacroDeclaredTwice(_) => 59,
+            SyntaxErrorKind::InvalidMacroName(_) => 60,
+            SyntaxErrorKind::InvalidMacroParameter(_) => 61,
+            SyntaxErrorKind::MissingArgument(_, _) => 62,
+            SyntaxErrorKind::TooManyArguments(_) => 63,
+            SyntaxErrorKind::MacroTooDeep(_) => 64,
+            SyntaxErrorKind::ExpectedRepetitionCount => 65,
+            SyntaxErrorKind::UnexpectedEndr => 66,
+            SyntaxErrorKind::RepetitionNeverEnded => 67,
+            SyntaxErrorKind::StructNeverEnded => 68,
+            SyntaxErrorKind::UnexpectedEnds => 69,
+            SyntaxErrorKind::UnexpectedStruct => 70,
+            SyntaxErrorKind::ExpectedField => 71,
+            SyntaxErrorKind::ExpectedAssertion(_) => 72,
+            SyntaxErrorKind::ExpectedMessage(_) => 73,
+            SyntaxErrorKind::ExpectedPrintList(_) => 74,
+            SyntaxErrorKind::AssertionFailed(_) => 75,
+            SyntaxErrorKind::UserError(_) => 76,
+            SyntaxErrorKind::InvalidPseudoOperands(_) => 77,
+            SyntaxErrorKind::ReadOnlyRegister(_) => 78,
+            SyntaxErrorKind::InvalidMemoryOperand(_) => 79,
+            SyntaxErrorKind::ExpectedMemoryAccess(_) => 80,
+            SyntaxErrorKind::ImmediateWithOffset => 81,
+            SyntaxErrorKind::ExpectedThreeOperands(_) => 82,
+            SyntaxErrorKind::ExpectedRegList(_) => 83,
+            SyntaxErrorKind::ReservedName(_) => 84,
+        }
+    }
+}
+
+pub enum SyntaxWarningKind {
+    MisalignedJumpTarget(String, u64),
+    User(String),
+    UnknownInterrupt(i64),
+}
+
+impl Debug for SyntaxWarningKind {
+    fn fmt(&self, f: &mut Formatter) -> core::result::Result<(), FmtErr> {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::MisalignedJumpTarget(lab, addr) => format!("The jump target \"{lab}\" is at the address {addr:#x}, which is not aligned on 4 bytes."),
+                Self::User(msg) => msg.to_string(),
+                Self::UnknownInterrupt(number) => format!("The interrupt {number} is not handled by the ZVM, the list is given by the option --help-syscalls."),
+            }
+        )
+    }
+}
+
+/// A warning does not stop the assembly, it is only displayed to the user
+pub struct SyntaxWarning {
+    line: usize,
+    warn: SyntaxWarningKind,
+    location: Option<Location>,
+}
+
+impl SyntaxWarning {
+    pub fn new(warn: SyntaxWarningKind, line: usize) -> Self {
+        Self {
+            line,
+            warn,
+            location: None,
+        }
+    }
+
+    /// Same as SyntaxError::locate
+    pub fn locate(mut self, locations: &[Location]) -> Self {
+        if self.line > 0 {
+            self.location = locations.get(self.line - 1).cloned();
         }
+        self
+    }
+}
+
+impl Debug for SyntaxWarning {
+    fn fmt(&self, f: &mut Formatter) -> core::result::Result<(), FmtErr> {
+        write_diagnostic(f, "Warning", self.line, &self.location, &self.warn)
     }
 }
 
@@ -121,11 +399,15 @@ pub type SyntaxResult<T> = core::result::Result<T, SyntaxError>;
 pub fn cast_result<T>(res: SyntaxResultKind<T>, line: usize) -> SyntaxResult<T> {
     match res {
         SyntaxResultKind::Ok(r) => SyntaxResult::Ok(r),
-        SyntaxResultKind::Err(err) => SyntaxResult::Err(SyntaxError { line, err }),
+        SyntaxResultKind::Err(err) => SyntaxResult::Err(SyntaxError {
+            line,
+            err,
+            location: None,
+        }),
     }
 }
 
-/// Returns true if the given name can be a label name. For now, only empty name are forbiddent.
+/// Returns true if the given name can be a label name. For now, only empty name and names containing operators or brackets are forbiddent.
 pub fn is_valid_label_name(name: &str) -> bool {
-    !name.is_empty()
+    !name.is_empty() && !name.contains(|c| OPERATOR_CHARS.contains(c) || c == '[' || c == ']')
 }
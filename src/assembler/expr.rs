@@ -0,0 +1,323 @@
+use super::{
+    errors::{SyntaxErrorKind, SyntaxResultKind},
+    word::parse_number,
+};
+
+/// Chars used by the operators of the expressions, a word containing one of them is interpreted as a part of an expression
+pub const OPERATOR_CHARS: &str = "+-*/%&|^~!<>=()";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UnaryOp {
+    /// '-'
+    Neg,
+    /// '~'
+    Not,
+    /// '!'
+    LogicalNot,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BinaryOp {
+    Mul,
+    Div,
+    Mod,
+    Add,
+    Sub,
+    Shl,
+    Shr,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Xor,
+    Or,
+    LogicalAnd,
+    LogicalOr,
+}
+
+impl BinaryOp {
+    fn from_token(op: &str) -> Option<Self> {
+        Some(match op {
+            "*" => Self::Mul,
+            "/" => Self::Div,
+            "%" => Self::Mod,
+            "+" => Self::Add,
+            "-" => Self::Sub,
+            "<<" => Self::Shl,
+            ">>" => Self::Shr,
+            "<" => Self::Lt,
+            "<=" => Self::Le,
+            ">" => Self::Gt,
+            ">=" => Self::Ge,
+            "==" => Self::Eq,
+            "!=" => Self::Ne,
+            "&" => Self::And,
+            "^" => Self::Xor,
+            "|" => Self::Or,
+            "&&" => Self::LogicalAnd,
+            "||" => Self::LogicalOr,
+            _ => return None,
+        })
+    }
+
+    /// The operators follow the precedence of the C language, the greater binds the tighter
+    fn precedence(self) -> u8 {
+        match self {
+            Self::Mul | Self::Div | Self::Mod => 10,
+            Self::Add | Self::Sub => 9,
+            Self::Shl | Self::Shr => 8,
+            Self::Lt | Self::Le | Self::Gt | Self::Ge => 7,
+            Self::Eq | Self::Ne => 6,
+            Self::And => 5,
+            Self::Xor => 4,
+            Self::Or => 3,
+            Self::LogicalAnd => 2,
+            Self::LogicalOr => 1,
+        }
+    }
+
+    fn apply(self, a: i64, b: i64) -> SyntaxResultKind<i64> {
+        Ok(match self {
+            Self::Mul => a.wrapping_mul(b),
+            Self::Div | Self::Mod if b == 0 => return Err(SyntaxErrorKind::DivisionByZero),
+            Self::Div => a.wrapping_div(b),
+            Self::Mod => a.wrapping_rem(b),
+            Self::Add => a.wrapping_add(b),
+            Self::Sub => a.wrapping_sub(b),
+            Self::Shl => a.wrapping_shl(b as u32),
+            Self::Shr => a.wrapping_shr(b as u32),
+            Self::Lt => (a < b) as i64,
+            Self::Le => (a <= b) as i64,
+            Self::Gt => (a > b) as i64,
+            Self::Ge => (a >= b) as i64,
+            Self::Eq => (a == b) as i64,
+            Self::Ne => (a != b) as i64,
+            Self::And => a & b,
+            Self::Xor => a ^ b,
+            Self::Or => a | b,
+            Self::LogicalAnd => (a != 0 && b != 0) as i64,
+            Self::LogicalOr => (a != 0 || b != 0) as i64,
+        })
+    }
+}
+
+/// Represent an expression computed at assembly time, such as `BUF_SIZE * 4`
+#[derive(Clone, Debug)]
+pub enum Expr {
+    Number(i64),
+    /// A label or a constant, resolved as late as possible
+    Symbol(String),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+enum Token {
+    Number(i64),
+    Symbol(String),
+    Op(String),
+}
+
+fn tokenize(s: &str) -> SyntaxResultKind<Vec<Token>> {
+    let mut res = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if OPERATOR_CHARS.contains(c) {
+            let mut op = String::from(c);
+            if let Some(&next) = chars.peek() {
+                op.push(next);
+                if BinaryOp::from_token(&op).is_some() {
+                    chars.next();
+                } else {
+                    op.pop();
+                }
+            }
+            res.push(Token::Op(op));
+            continue;
+        }
+        let mut word = String::from(c);
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() || OPERATOR_CHARS.contains(c) {
+                break;
+            }
+            word.push(c);
+            chars.next();
+        }
+        res.push(match parse_number(&word) {
+            Some(x) => Token::Number(x as i64),
+            None if word.starts_with(|c: char| c.is_ascii_digit()) => {
+                return Err(SyntaxErrorKind::InvalidNumber(word))
+            }
+            None => Token::Symbol(word),
+        });
+    }
+    Ok(res)
+}
+
+struct Parser {
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+    fn parse_operand(&mut self) -> Option<Expr> {
+        Some(match self.tokens.next()? {
+            Token::Number(x) => Expr::Number(x),
+            Token::Symbol(s) => Expr::Symbol(s),
+            Token::Op(op) => match op.as_str() {
+                "-" => Expr::Unary(UnaryOp::Neg, Box::new(self.parse_operand()?)),
+                "~" => Expr::Unary(UnaryOp::Not, Box::new(self.parse_operand()?)),
+                "!" => Expr::Unary(UnaryOp::LogicalNot, Box::new(self.parse_operand()?)),
+                "+" => self.parse_operand()?,
+                "(" => {
+                    let expr = self.parse(0)?;
+                    match self.tokens.next()? {
+                        Token::Op(op) if op == ")" => expr,
+                        _ => return None,
+                    }
+                }
+                _ => return None,
+            },
+        })
+    }
+
+    /// Parse the operations whose precedence is at least the given one
+    fn parse(&mut self, min_precedence: u8) -> Option<Expr> {
+        let mut lhs = self.parse_operand()?;
+        while let Some(Token::Op(op)) = self.tokens.peek() {
+            let op = match BinaryOp::from_token(op) {
+                Some(op) if op.precedence() >= min_precedence => op,
+                _ => break,
+            };
+            self.tokens.next();
+            let rhs = self.parse(op.precedence() + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Some(lhs)
+    }
+}
+
+impl TryFrom<&str> for Expr {
+    type Error = SyntaxErrorKind;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?.into_iter().peekable(),
+        };
+        match parser.parse(0) {
+            Some(expr) if parser.tokens.next().is_none() => Ok(expr),
+            _ => Err(SyntaxErrorKind::InvalidExpression(s.to_string())),
+        }
+    }
+}
+
+impl Expr {
+    /// Returns all the symbols used by the expression
+    pub fn symbols(&self) -> Vec<&String> {
+        match self {
+            Self::Number(_) => Vec::new(),
+            Self::Symbol(s) => vec![s],
+            Self::Unary(_, e) => e.symbols(),
+            Self::Binary(_, a, b) => {
+                let mut res = a.symbols();
+                res.append(&mut b.symbols());
+                res
+            }
+        }
+    }
+
+    /// Replace each symbol known by the resolver by its value
+    pub fn substitute(&mut self, resolve: &impl Fn(&str) -> Option<i64>) {
+        match self {
+            Self::Number(_) => (),
+            Self::Symbol(s) => {
+                if let Some(x) = resolve(s) {
+                    *self = Self::Number(x)
+                }
+            }
+            Self::Unary(_, e) => e.substitute(resolve),
+            Self::Binary(_, a, b) => {
+                a.substitute(resolve);
+                b.substitute(resolve)
+            }
+        }
+    }
+
+    /// Compute the value of the expression, each symbol has to be known by the resolver
+    pub fn eval(&self, resolve: &impl Fn(&str) -> Option<i64>) -> SyntaxResultKind<i64> {
+        Ok(match self {
+            Self::Number(x) => *x,
+            Self::Symbol(s) => match resolve(s) {
+                Some(x) => x,
+                None => return Err(SyntaxErrorKind::LabelIsNotDeclared(s.to_string())),
+            },
+            Self::Unary(op, e) => {
+                let x = e.eval(resolve)?;
+                match op {
+                    UnaryOp::Neg => x.wrapping_neg(),
+                    UnaryOp::Not => !x,
+                    UnaryOp::LogicalNot => (x == 0) as i64,
+                }
+            }
+            Self::Binary(op, a, b) => op.apply(a.eval(resolve)?, b.eval(resolve)?)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Evaluate an expression where SIZE is 8 and the other symbols are not known
+    fn eval(s: &str) -> SyntaxResultKind<i64> {
+        Expr::try_from(s)?.eval(&|name| (name == "SIZE").then_some(8))
+    }
+
+    #[test]
+    fn precedence_follows_c() {
+        assert_eq!(eval("1 + 2 * 3").unwrap(), 7);
+        assert_eq!(eval("(1 + 2) * 3").unwrap(), 9);
+        assert_eq!(eval("1 << 2 + 1").unwrap(), 8);
+        assert_eq!(eval("0x12345678 >> 16 & 0xFFFF").unwrap(), 0x1234);
+        assert_eq!(eval("1 | 2 ^ 3 & 5").unwrap(), 1 | (2 ^ (3 & 5)));
+        assert_eq!(eval("1 < 2 == 1").unwrap(), 1);
+        assert_eq!(eval("0 || 1 && 0").unwrap(), 0);
+        assert_eq!(eval("10 - 4 - 3").unwrap(), 3);
+        assert_eq!(eval("SIZE * 4 - 1").unwrap(), 31);
+    }
+
+    #[test]
+    fn unary_operators() {
+        assert_eq!(eval("-SIZE").unwrap(), -8);
+        assert_eq!(eval("~0").unwrap(), -1);
+        assert_eq!(eval("!SIZE").unwrap(), 0);
+        assert_eq!(eval("-2 * 3").unwrap(), -6);
+    }
+
+    #[test]
+    fn invalid_expressions() {
+        assert!(matches!(
+            eval("1 / 0"),
+            Err(SyntaxErrorKind::DivisionByZero)
+        ));
+        assert!(matches!(
+            eval("UNKNOWN + 1"),
+            Err(SyntaxErrorKind::LabelIsNotDeclared(_))
+        ));
+        for s in ["1 +", "(1 + 2", "1 2", "* 3"] {
+            assert!(eval(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn known_symbols_are_substituted() {
+        let mut expr = Expr::try_from("SIZE + end").unwrap();
+        expr.substitute(&|name| (name == "SIZE").then_some(8));
+        assert_eq!(expr.symbols(), [&String::from("end")]);
+        assert_eq!(expr.eval(&|_| Some(4)).unwrap(), 12);
+    }
+}
@@ -0,0 +1,547 @@
+use super::{
+    errors::{SyntaxErrorKind, SyntaxResultKind},
+    op_codes::{OpCode, ALU_SHIFT, CONDITIONAL, EXTENDED, EXT_SHIFT, JCODE, THREE_OPERANDS, WIDTH},
+    word::Word,
+};
+
+/// The kind of an operand accepted by an instruction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    /// A register written by the instruction, it can't be read only
+    Dst,
+    /// A register read by the instruction
+    Reg,
+    /// A register or an immediate value
+    RegOrImm,
+    /// The address of a value read, a register, an immediate value or a memory operand
+    Address,
+    /// The address of a value written, a register or a memory operand
+    Target,
+    /// A list of registers written by the instruction, it is the only operand of its form
+    Registers,
+}
+
+impl Operand {
+    fn accepts(self, w: &Word) -> bool {
+        match self {
+            Self::Dst | Self::Reg | Self::Registers => w.is_reg(),
+            Self::RegOrImm => w.is_reg_or_imm(),
+            Self::Address => w.is_reg_or_imm() || w.is_memory(),
+            Self::Target => w.is_reg() || w.is_memory(),
+        }
+    }
+
+    /// Returns the name of the operand in the --help-isa output
+    fn name(self) -> &'static str {
+        match self {
+            Self::Dst | Self::Reg => "reg",
+            Self::RegOrImm => "reg|imm",
+            Self::Address => "reg|imm|[mem]",
+            Self::Target => "reg|[mem]",
+            Self::Registers => "reg, ...",
+        }
+    }
+}
+
+/// The code identifying the instruction in the binary
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Code {
+    /// An op code on the bits 31 to 27
+    Op(u8),
+    /// A code on the bits 26 to 21 under the EXTENDED op code, the operands are laid out 6 bits lower than the ones of an op code
+    Ext(u8),
+    /// The op code of the two operands form, stored on the bits 18 to 15 under the THREE_OPERANDS op code
+    Alu(u8),
+}
+
+impl Code {
+    /// Returns the bits of the code in the instruction, with the number of bits the operands are moved down under it
+    pub fn bits(self) -> (u32, u32) {
+        match self {
+            Self::Op(n) => ((n as u32) << 27, 0),
+            Self::Ext(n) => (
+                (EXTENDED as u32) << 27 | (n as u32) << EXT_SHIFT,
+                27 - EXT_SHIFT,
+            ),
+            Self::Alu(n) => ((THREE_OPERANDS as u32) << 27 | (n as u32) << ALU_SHIFT, 0),
+        }
+    }
+}
+
+/// How the operands are laid out under the code, see the README for the bits of each layout
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    /// Nothing is stored
+    Nothing,
+    /// A register or an immediate value under a flag
+    Single,
+    /// No operand, encoded as the single layout with the immediate value 1
+    Once,
+    /// The condition, then a register or an immediate value under a flag
+    Jump,
+    /// The destination register, then a register or an immediate value under a flag
+    Double,
+    /// The single register is both the destination and the source of the double layout
+    InPlace,
+    /// The destination register, the first source register, then a register or an immediate value under a flag
+    Triple,
+    /// The register receiving the value, then the address as a base register with an offset or as an immediate value under a flag
+    Load,
+    /// The base register of the address, then the value as a register with an offset or as an immediate value under a flag
+    Store,
+    /// The load layout with the width of the access before the flag
+    SizedLoad,
+    /// The store layout with the width of the access before the flag
+    SizedStore,
+    /// The condition, the destination register, then a register or an immediate value under a flag
+    CondMove,
+    /// The condition, the destination register and the bit telling it is a set
+    CondSet,
+    /// The mask of the registers, the bit of each one is set
+    Mask,
+}
+
+impl Layout {
+    /// Returns true if the layout is a shorthand of an other one, the disassembler displays the other one
+    pub fn is_shorthand(self) -> bool {
+        matches!(self, Self::Once | Self::InPlace)
+    }
+}
+
+/// The operands of an instruction with the way they are encoded
+pub struct Form {
+    pub operands: &'static [Operand],
+    pub code: Code,
+    pub layout: Layout,
+}
+
+const fn form(operands: &'static [Operand], code: Code, layout: Layout) -> Form {
+    Form {
+        operands,
+        code,
+        layout,
+    }
+}
+
+impl Form {
+    /// Returns true if the operands of the line match the form
+    pub fn accepts(&self, line: &[&Word]) -> bool {
+        match self.operands {
+            [Operand::Registers] => !line.is_empty() && line.iter().all(|w| w.is_reg()),
+            operands => {
+                operands.len() == line.len()
+                    && operands.iter().zip(line).all(|(op, w)| op.accepts(w))
+            }
+        }
+    }
+
+    /// Checks that the registers written by the instruction are not read only
+    pub fn check_destinations(&self, line: &[&Word]) -> SyntaxResultKind<()> {
+        let written = match self.operands {
+            [Operand::Registers] => line.to_vec(),
+            operands => operands
+                .iter()
+                .zip(line)
+                .filter(|(op, _)| **op == Operand::Dst)
+                .map(|(_, w)| *w)
+                .collect(),
+        };
+        match written
+            .iter()
+            .filter_map(|w| w.get_reg())
+            .find(|reg| !reg.is_writable())
+        {
+            Some(reg) => Err(SyntaxErrorKind::ReadOnlyRegister(reg)),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the error raised when the operands of a line do not match any form, describing this one
+    pub fn expected(&self, op_code: OpCode) -> SyntaxErrorKind {
+        match self.operands {
+            [] => SyntaxErrorKind::ExpectedNothing(op_code),
+            [Operand::Dst | Operand::Reg] => SyntaxErrorKind::ExpectedReg(op_code),
+            [Operand::RegOrImm] => SyntaxErrorKind::ExpectedRegOrImm(op_code),
+            [Operand::Registers] => SyntaxErrorKind::ExpectedRegList(op_code),
+            [Operand::Dst | Operand::Reg, Operand::RegOrImm] => {
+                SyntaxErrorKind::ExpectedRegImmOrReg(op_code)
+            }
+            [_, _, _] => SyntaxErrorKind::ExpectedThreeOperands(op_code),
+            _ => SyntaxErrorKind::ExpectedMemoryAccess(op_code),
+        }
+    }
+}
+
+/// An instruction of the ISA, several mnemonics share it when it takes a condition or a width
+pub struct Instruction {
+    pub mnemonics: &'static [(&'static str, OpCode)],
+    /// The accepted forms, the first one matching the operands is used
+    pub forms: &'static [Form],
+    pub description: &'static str,
+}
+
+use Code::{Alu, Ext, Op};
+use Layout::*;
+use Operand::*;
+
+/// The forms of an arithmetic or bitwise instruction, with two or three operands
+macro_rules! alu_forms {
+    ($n:expr) => {
+        &[
+            form(&[Dst, RegOrImm], Op($n), Double),
+            form(&[Dst, Reg, RegOrImm], Alu($n), Triple),
+        ]
+    };
+}
+
+/// Every instruction of the Nuzima architecture, the parser, the validator, the encoder, the disassembler and the --help-isa output are driven by this table
+pub const ISA: &[Instruction] = &[
+    Instruction {
+        mnemonics: &[("add", OpCode::ADD)],
+        forms: alu_forms!(0),
+        description: "Add two values",
+    },
+    Instruction {
+        mnemonics: &[("mul", OpCode::MUL)],
+        forms: alu_forms!(1),
+        description: "Multiply two values, keeping the low 32 bits",
+    },
+    Instruction {
+        mnemonics: &[("sub", OpCode::SUB)],
+        forms: alu_forms!(2),
+        description: "Subtract one value from another",
+    },
+    Instruction {
+        mnemonics: &[("div", OpCode::DIV)],
+        forms: alu_forms!(3),
+        description: "Divide one value by another, as signed values",
+    },
+    Instruction {
+        mnemonics: &[("mod", OpCode::MOD)],
+        forms: alu_forms!(4),
+        description: "Remainder of the signed division, it has the sign of the dividend",
+    },
+    Instruction {
+        mnemonics: &[("neg", OpCode::NEG)],
+        forms: &[
+            form(&[], Op(5), Nothing),
+            form(&[Dst], Ext(13), Single),
+        ],
+        description: "Negate a register, the historical form has no operand",
+    },
+    Instruction {
+        mnemonics: &[("shl", OpCode::SHL)],
+        forms: alu_forms!(6),
+        description: "Shift bits to the left",
+    },
+    Instruction {
+        mnemonics: &[("shr", OpCode::SHR)],
+        forms: alu_forms!(7),
+        description: "Shift bits to the right, filling with zeros",
+    },
+    Instruction {
+        mnemonics: &[("and", OpCode::AND)],
+        forms: alu_forms!(8),
+        description: "Bitwise and",
+    },
+    Instruction {
+        mnemonics: &[("or", OpCode::OR)],
+        forms: alu_forms!(9),
+        description: "Bitwise or",
+    },
+    Instruction {
+        mnemonics: &[("xor", OpCode::XOR)],
+        forms: alu_forms!(10),
+        description: "Bitwise xor",
+    },
+    Instruction {
+        mnemonics: &[("not", OpCode::NOT)],
+        forms: &[
+            form(&[Dst, RegOrImm], Op(11), Double),
+            form(&[Dst], Op(11), InPlace),
+        ],
+        description: "Bitwise not of the source, or of the register in place",
+    },
+    Instruction {
+        mnemonics: &[
+            ("jmp", OpCode::JMP(JCODE::JMP)),
+            ("je", OpCode::JMP(JCODE::JE)),
+            ("jne", OpCode::JMP(JCODE::JNE)),
+            ("jh", OpCode::JMP(JCODE::JH)),
+            ("jl", OpCode::JMP(JCODE::JL)),
+            ("jhe", OpCode::JMP(JCODE::JHE)),
+            ("jle", OpCode::JMP(JCODE::JLE)),
+            ("ja", OpCode::JMP(JCODE::JA)),
+            ("jb", OpCode::JMP(JCODE::JB)),
+            ("jae", OpCode::JMP(JCODE::JAE)),
+            ("jbe", OpCode::JMP(JCODE::JBE)),
+        ],
+        forms: &[form(&[RegOrImm], Op(12), Jump)],
+        description: "Jump, always or if the condition set by the last cmp holds",
+    },
+    Instruction {
+        mnemonics: &[("int", OpCode::INT)],
+        forms: &[form(&[RegOrImm], Op(13), Single)],
+        description: "Trigger an interrupt of the ZVM, see --help-syscalls",
+    },
+    Instruction {
+        mnemonics: &[("push", OpCode::PUSH)],
+        forms: &[form(&[RegOrImm], Op(14), Single)],
+        description: "Push a value onto the stack",
+    },
+    Instruction {
+        mnemonics: &[("pop", OpCode::POP)],
+        forms: &[form(&[Dst], Op(15), Single)],
+        description: "Pop a value from the stack into a register",
+    },
+    Instruction {
+        mnemonics: &[("ret", OpCode::RET)],
+        forms: &[form(&[], Op(16), Nothing)],
+        description: "Return from a subroutine",
+    },
+    Instruction {
+        mnemonics: &[("call", OpCode::CALL)],
+        forms: &[form(&[RegOrImm], Op(17), Single)],
+        description: "Call a subroutine",
+    },
+    Instruction {
+        mnemonics: &[("mov", OpCode::MOV)],
+        forms: &[form(&[Dst, RegOrImm], Op(18), Double)],
+        description: "Move a value in a register",
+    },
+    Instruction {
+        mnemonics: &[("rd", OpCode::READ)],
+        forms: &[form(&[Dst, Address], Op(19), Load)],
+        description: "Read a word from memory",
+    },
+    Instruction {
+        mnemonics: &[("wr", OpCode::WRITE)],
+        forms: &[form(&[Target, RegOrImm], Op(20), Store)],
+        description: "Write a word to memory",
+    },
+    Instruction {
+        mnemonics: &[("cmp", OpCode::CMP)],
+        forms: &[form(&[Reg, RegOrImm], Op(21), Double)],
+        description: "Compare two values, the result is stored in rcond",
+    },
+    Instruction {
+        mnemonics: &[("clear", OpCode::CLEAR)],
+        forms: &[
+            form(&[], Op(22), Nothing),
+            form(&[Registers], Ext(14), Mask),
+        ],
+        description: "Set the given registers to 0, or all of them without operand",
+    },
+    Instruction {
+        mnemonics: &[("halt", OpCode::HALT)],
+        forms: &[form(&[], Op(23), Nothing)],
+        description: "Halt the execution of the program",
+    },
+    Instruction {
+        mnemonics: &[("trace", OpCode::TRACE)],
+        forms: &[form(&[], Op(24), Nothing)],
+        description: "Output debug information",
+    },
+    Instruction {
+        mnemonics: &[("dup", OpCode::DUP)],
+        forms: &[form(&[], Op(25), Nothing)],
+        description: "Duplicate the top value of the stack",
+    },
+    Instruction {
+        mnemonics: &[("swap", OpCode::SWAP)],
+        forms: &[form(&[], Op(26), Nothing)],
+        description: "Swap the top two values of the stack",
+    },
+    Instruction {
+        mnemonics: &[
+            ("rdb", OpCode::LOAD(WIDTH::B)),
+            ("rdbs", OpCode::LOAD(WIDTH::BS)),
+            ("rdh", OpCode::LOAD(WIDTH::H)),
+            ("rdhs", OpCode::LOAD(WIDTH::HS)),
+        ],
+        forms: &[form(&[Dst, Address], Op(27), SizedLoad)],
+        description: "Read a byte or a half word from memory, zero extended or sign extended with the s suffix",
+    },
+    Instruction {
+        mnemonics: &[
+            ("wrb", OpCode::STORE(WIDTH::B)),
+            ("wrh", OpCode::STORE(WIDTH::H)),
+        ],
+        forms: &[form(&[Target, RegOrImm], Op(28), SizedStore)],
+        description: "Write the low byte or half word of a value to memory",
+    },
+    Instruction {
+        mnemonics: &[
+            ("cmove", OpCode::CMOV(JCODE::JE)),
+            ("cmovne", OpCode::CMOV(JCODE::JNE)),
+            ("cmovh", OpCode::CMOV(JCODE::JH)),
+            ("cmovl", OpCode::CMOV(JCODE::JL)),
+            ("cmovhe", OpCode::CMOV(JCODE::JHE)),
+            ("cmovle", OpCode::CMOV(JCODE::JLE)),
+            ("cmova", OpCode::CMOV(JCODE::JA)),
+            ("cmovb", OpCode::CMOV(JCODE::JB)),
+            ("cmovae", OpCode::CMOV(JCODE::JAE)),
+            ("cmovbe", OpCode::CMOV(JCODE::JBE)),
+        ],
+        forms: &[form(&[Dst, RegOrImm], Op(CONDITIONAL), CondMove)],
+        description: "Move a value in a register if the condition set by the last cmp holds",
+    },
+    Instruction {
+        mnemonics: &[
+            ("sete", OpCode::SET(JCODE::JE)),
+            ("setne", OpCode::SET(JCODE::JNE)),
+            ("seth", OpCode::SET(JCODE::JH)),
+            ("setl", OpCode::SET(JCODE::JL)),
+            ("sethe", OpCode::SET(JCODE::JHE)),
+            ("setle", OpCode::SET(JCODE::JLE)),
+            ("seta", OpCode::SET(JCODE::JA)),
+            ("setb", OpCode::SET(JCODE::JB)),
+            ("setae", OpCode::SET(JCODE::JAE)),
+            ("setbe", OpCode::SET(JCODE::JBE)),
+        ],
+        forms: &[form(&[Dst], Op(CONDITIONAL), CondSet)],
+        description: "Set a register to 1 if the condition set by the last cmp holds, else to 0",
+    },
+    Instruction {
+        mnemonics: &[("drop", OpCode::DROP)],
+        forms: &[
+            form(&[RegOrImm], Ext(0), Single),
+            form(&[], Ext(0), Once),
+        ],
+        description: "Remove the given number of values from the top of the stack, 1 by default",
+    },
+    Instruction {
+        mnemonics: &[("over", OpCode::OVER)],
+        forms: &[form(&[], Ext(1), Nothing)],
+        description: "Push a copy of the second value of the stack",
+    },
+    Instruction {
+        mnemonics: &[("rot", OpCode::ROT)],
+        forms: &[form(&[], Ext(2), Nothing)],
+        description: "Move the third value of the stack on the top",
+    },
+    Instruction {
+        mnemonics: &[("pick", OpCode::PICK)],
+        forms: &[form(&[RegOrImm], Ext(3), Single)],
+        description: "Push a copy of the value at the given depth of the stack, pick 0 is dup",
+    },
+    Instruction {
+        mnemonics: &[("rol", OpCode::ROL)],
+        forms: &[form(&[Dst, RegOrImm], Ext(4), Double)],
+        description: "Rotate bits to the left",
+    },
+    Instruction {
+        mnemonics: &[("ror", OpCode::ROR)],
+        forms: &[form(&[Dst, RegOrImm], Ext(5), Double)],
+        description: "Rotate bits to the right",
+    },
+    Instruction {
+        mnemonics: &[("sar", OpCode::SAR)],
+        forms: &[form(&[Dst, RegOrImm], Ext(6), Double)],
+        description: "Shift bits to the right, filling with the sign bit",
+    },
+    Instruction {
+        mnemonics: &[("divu", OpCode::DIVU)],
+        forms: &[form(&[Dst, RegOrImm], Ext(7), Double)],
+        description: "Divide one value by another, as unsigned values",
+    },
+    Instruction {
+        mnemonics: &[("modu", OpCode::MODU)],
+        forms: &[form(&[Dst, RegOrImm], Ext(8), Double)],
+        description: "Remainder of the unsigned division",
+    },
+    Instruction {
+        mnemonics: &[("mulh", OpCode::MULH)],
+        forms: &[form(&[Dst, RegOrImm], Ext(9), Double)],
+        description: "High 32 bits of the product of two signed values",
+    },
+    Instruction {
+        mnemonics: &[("mulhu", OpCode::MULHU)],
+        forms: &[form(&[Dst, RegOrImm], Ext(10), Double)],
+        description: "High 32 bits of the product of two unsigned values",
+    },
+    Instruction {
+        mnemonics: &[("popcnt", OpCode::POPCNT)],
+        forms: &[form(&[Dst, RegOrImm], Ext(11), Double)],
+        description: "Count the bits set in the source",
+    },
+    Instruction {
+        mnemonics: &[("clz", OpCode::CLZ)],
+        forms: &[form(&[Dst, RegOrImm], Ext(12), Double)],
+        description: "Count the leading zero bits of the source, 32 for zero",
+    },
+];
+
+/// Returns the instructions with their operands and their encoding, one line per instruction followed by one line per form
+fn isa_help() -> String {
+    let mut res = String::new();
+    for instruction in ISA {
+        let mnemonics = instruction
+            .mnemonics
+            .iter()
+            .map(|(m, _)| *m)
+            .collect::<Vec<_>>()
+            .join(", ");
+        res += &format!("{mnemonics}: {}\n", instruction.description);
+        for form in instruction.forms {
+            let operands = form
+                .operands
+                .iter()
+                .map(|op| op.name())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let code = match form.code {
+                Op(n) => format!("op {n}"),
+                Ext(n) => format!("ext {n}"),
+                Alu(n) => format!("alu {n}"),
+            };
+            let operands = if operands.is_empty() {
+                String::from("-")
+            } else {
+                operands
+            };
+            res += &format!("    {operands:<20} {code:<8} {:?}\n", form.layout);
+        }
+    }
+    res
+}
+
+/// Print the instructions with their operands and their encoding, as given by the option --help-isa
+pub fn print_isa() {
+    print!("{}", isa_help());
+}
+
+/// Returns the encoding of mov r0, r0, used as a no operation instruction to fill the gaps in the code
+pub fn nop() -> u32 {
+    let form = OpCode::MOV
+        .instruction()
+        .forms
+        .iter()
+        .find(|form| form.layout == Layout::Double)
+        .unwrap();
+    // The registers of the operands are r0 and the flag is clear, so only the code is set
+    form.code.bits().0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readme_lists_the_isa() {
+        let readme = include_str!("../../README.md");
+        let listing: String = isa_help()
+            .lines()
+            .map(|line| format!("    {line}\n"))
+            .collect();
+        assert!(
+            readme.contains(&listing),
+            "The list of the instructions in the README differs from the output of --help-isa"
+        );
+    }
+
+    #[test]
+    fn nop_is_mov_r0_r0() {
+        assert_eq!(nop(), 18 << 27);
+    }
+}
@@ -1,19 +1,74 @@
 use std::{collections::HashMap, vec::IntoIter};
 
 use super::{
+    directives::{Directive, Section, NOP_FILL},
     errors::{SyntaxErrorKind, SyntaxResultKind},
-    op_codes::OpCode,
-    word::{Word, WordContent},
+    expr::Expr,
+    isa::{nop, Layout},
+    op_codes::{
+        OpCode, ABSOLUTE_BASE, ALU_SHIFT, COND_DST_SHIFT, DST_SHIFT, JCODE_SHIFT, OFFSET_SHIFT,
+        SET_BIT, SRC_SHIFT, WIDTH_SHIFT,
+    },
+    word::{parse_memory, Word, WordContent},
 };
 
+#[derive(Default)]
 pub struct Line {
     instruction: Vec<Word>,
+    /// The address of the first byte emitted by the line, computed once all the lines are known
+    address: u64,
+    /// The section in which the line is laid out
+    section: Section,
+    /// The bytes of the file embedded by an .incbin directive
+    incbin: Vec<u8>,
 }
 
 impl Line {
     pub fn get(&self) -> &Vec<Word> {
         &self.instruction
     }
+
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    pub fn set_address(&mut self, address: u64) {
+        self.address = address
+    }
+
+    pub fn section(&self) -> Section {
+        self.section
+    }
+
+    pub fn set_section(&mut self, section: Section) {
+        self.section = section
+    }
+
+    pub fn set_incbin(&mut self, bytes: Vec<u8>) {
+        self.incbin = bytes
+    }
+}
+
+/// Returns the first multiple of the boundary greater or equal to the address
+pub fn align_up(address: u64, boundary: u64) -> u64 {
+    address.div_ceil(boundary) * boundary
+}
+
+/// Generate size bytes of padding starting at the given address. The nop fill pad with null bytes until the address is aligned on 4 bytes and then with nop instructions.
+fn padding(address: u64, size: usize, fill: &WordContent) -> Vec<u8> {
+    match fill {
+        WordContent::Number(x) => vec![*x as u8; size],
+        _ => {
+            let end = address + size as u64;
+            let mut res = vec![0; (align_up(address, 4).min(end) - address) as usize];
+            let nop = nop().to_be_bytes();
+            while res.len() + 4 <= size {
+                res.extend_from_slice(&nop)
+            }
+            res.resize(size, 0);
+            res
+        }
+    }
 }
 
 macro_rules! unwrap_or_ret {
@@ -25,7 +80,70 @@ macro_rules! unwrap_or_ret {
     };
 }
 
-fn extract(words: Vec<Word>) -> SyntaxResultKind<Vec<Word>> {
+/// Build a single operand from the words found between two commas. A single word is kept as it is, several words are rebuilt as an expression.
+fn build_operand(group: Vec<Word>) -> SyntaxResultKind<Word> {
+    let mut pure_content = String::new();
+    for w in &group {
+        pure_content.push_str(&w.pure_content[..w.pure_content.len() - 1]);
+    }
+    let mut words = group
+        .into_iter()
+        .filter(|w| !matches!(w.content, WordContent::Empty))
+        .collect::<Vec<_>>();
+    match words.len() {
+        0 => Err(SyntaxErrorKind::SyntaxError),
+        1 if !matches!(words[0].content, WordContent::ExprPart(_)) => Ok(words.pop().unwrap()),
+        _ if words[0].get_expr_part().is_some_and(|s| s.starts_with('[')) => {
+            let text = words
+                .iter()
+                .map(|w| &w.pure_content[1..w.pure_content.len() - 1])
+                .collect::<Vec<_>>()
+                .join(" ");
+            let sep = words.last().unwrap().sep();
+            pure_content.push(words.last().unwrap().pure_content.chars().last().unwrap());
+            Ok(Word::new(parse_memory(&text)?, pure_content, sep))
+        }
+        _ => {
+            let mut text = Vec::new();
+            for w in &words {
+                text.push(w.get_expr_part().ok_or(SyntaxErrorKind::SyntaxError)?);
+            }
+            let sep = words.last().unwrap().sep();
+            pure_content.push(words.last().unwrap().pure_content.chars().last().unwrap());
+            Ok(Word::new(
+                WordContent::Expr(Expr::try_from(&text.join(" ") as &str)?),
+                pure_content,
+                sep,
+            ))
+        }
+    }
+}
+
+/// Extract a list of operands separated by commas, an operand can be made of several words if it is an expression
+fn extract_operands(words: impl Iterator<Item = Word>) -> SyntaxResultKind<Vec<Word>> {
+    let mut res = Vec::new();
+    let mut group = Vec::new();
+    for w in words {
+        let ends_with_comma = w.ends_with_comma();
+        group.push(w);
+        if ends_with_comma {
+            res.push(build_operand(std::mem::take(&mut group))?);
+        }
+    }
+    if group
+        .iter()
+        .any(|w| !matches!(w.content, WordContent::Empty))
+        || !res.is_empty()
+    {
+        res.push(build_operand(group)?);
+    }
+    Ok(res)
+}
+
+fn extract(
+    words: Vec<Word>,
+    resolve: &impl Fn(&str) -> Option<i64>,
+) -> SyntaxResultKind<Vec<Word>> {
     let mut words = words.into_iter();
 
     let mut res = Vec::new();
@@ -34,7 +152,7 @@ fn extract(words: Vec<Word>) -> SyntaxResultKind<Vec<Word>> {
                             checker: Box<dyn Fn(String, bool) -> SyntaxResultKind<()>>|
      -> SyntaxResultKind<Option<Word>> {
         let mut skiped = String::new();
-        while let Some(w) = words.next() {
+        for w in words.by_ref() {
             if let WordContent::Empty = w.content {
                 skiped.push_str(&w.pure_content)
             } else {
@@ -87,88 +205,313 @@ fn extract(words: Vec<Word>) -> SyntaxResultKind<Vec<Word>> {
                 return Err(SyntaxErrorKind::SyntaxError);
             }
         }
-        WordContent::OpCode(op_code) => {
-            let mut rest = Vec::new();
-            if let Some(w) = skip_empty_words(&mut words, nb_occur_checker("", 0))? {
-                rest.push(w);
-                if let Some(w) = skip_empty_words(
-                    &mut words,
-                    Box::new(
-                        |skiped, eol| match nb_occur_checker("", 0)(skiped.clone(), eol) {
-                            Ok(_) => {
-                                if eol {
-                                    Ok(())
-                                } else {
-                                    Err(SyntaxErrorKind::SyntaxError)
-                                }
-                            }
-                            Err(_) => {
-                                if eol {
-                                    Err(SyntaxErrorKind::SyntaxError)
-                                } else {
-                                    nb_occur_checker(",", 1)(skiped, eol)
-                                }
-                            }
-                        },
-                    ),
-                )? {
-                    rest.push(w)
-                }
+        WordContent::OpCode(_) | WordContent::Directive(_) => {
+            if res.last().unwrap().ends_with_comma() {
+                return Err(SyntaxErrorKind::SyntaxError);
+            }
+            let mut rest = extract_operands(words)?;
+            let word = res.last().unwrap();
+            let names = word.get_directive().map_or(0, |d| d.name_operands());
+            for w in rest.iter_mut().skip(names) {
+                w.fold_constants(resolve)?;
+            }
+            match word.content {
+                WordContent::OpCode(op_code) => op_code.check_compatibility(&rest)?,
+                WordContent::Directive(directive) => directive.check_compatibility(&rest)?,
+                _ => unreachable!(),
             }
-
-            op_code.check_compatibility(&rest)?;
             res.append(&mut rest);
         }
+        WordContent::Label(ref lab) if lab.starts_with('.') => {
+            return Err(SyntaxErrorKind::InvalidDirective(lab.to_string()))
+        }
         _ => return Err(SyntaxErrorKind::SyntaxError),
     }
     Ok(res)
 }
 
-impl TryFrom<Vec<Word>> for Line {
-    type Error = SyntaxErrorKind;
-    fn try_from(instruction: Vec<Word>) -> Result<Self, Self::Error> {
+impl Line {
+    /// Build a line from its words, the constants known by the resolver are replaced by their value
+    pub fn new(
+        instruction: Vec<Word>,
+        resolve: &impl Fn(&str) -> Option<i64>,
+    ) -> SyntaxResultKind<Self> {
         Ok(Self {
-            instruction: extract(instruction)?,
+            instruction: extract(instruction, resolve)?,
+            address: 0,
+            section: Section::default(),
+            incbin: Vec::new(),
         })
     }
+
+    /// Replace the constants known by the resolver in the operands, for the constants that can be used before their declaration
+    pub fn fold_constants(
+        &mut self,
+        resolve: &impl Fn(&str) -> Option<i64>,
+    ) -> SyntaxResultKind<()> {
+        let start = match self.instruction.iter().position(|w| !w.is_label_decl()) {
+            Some(start) => start,
+            None => return Ok(()),
+        };
+        let names = self.instruction[start]
+            .get_directive()
+            .map_or(0, |d| d.name_operands());
+        for w in self.instruction.iter_mut().skip(start + 1 + names) {
+            w.fold_constants(resolve)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the immediate value stored on the given number of bits, it is signed as the disassembler reads it back
+fn encode_imm(value: i64, bits: u32) -> SyntaxResultKind<u32> {
+    if value < -(1 << (bits - 1)) || value >= 1 << (bits - 1) {
+        Err(SyntaxErrorKind::ValueOutOfRange(value))
+    } else {
+        Ok(value as u32 & ((1 << bits) - 1))
+    }
+}
+
+/// Returns the absolute address stored on the given number of bits, it is unsigned
+fn encode_address(address: i64, bits: u32) -> SyntaxResultKind<u32> {
+    if address < 0 || address >= 1 << bits {
+        Err(SyntaxErrorKind::ValueOutOfRange(address))
+    } else {
+        Ok(address as u32)
+    }
 }
 
+/// A register is stored in the lowest bits, an immediate value uses all the bits under the flag
 macro_rules! inj_reg_or_imm {
     ($w:expr, $label:ident, $flag_shift:expr) => {
         match &$w.content {
             WordContent::Register(reg) => Into::<u8>::into(*reg) as u32,
-            WordContent::Number(x) => 1 << $flag_shift | *x as u32,
-            WordContent::Label(lab) => 1 << $flag_shift | *$label.get(lab).unwrap() as u32,
-            _ => unreachable!(),
+            _ => 1 << $flag_shift | encode_imm($w.get_value($label)?, $flag_shift)?,
         }
     };
 }
 
+/// Encode a register with the offset of a memory operand, stored on the bits under the flag. The offset from a base register is signed, an absolute address is not.
+fn inj_reg_with_offset(
+    reg: u8,
+    offset: i64,
+    signed: bool,
+    flag_shift: u32,
+) -> SyntaxResultKind<u32> {
+    let bits = flag_shift - OFFSET_SHIFT;
+    let offset = match signed {
+        true => encode_imm(offset, bits)?,
+        false => encode_address(offset, bits)?,
+    };
+    Ok(offset << OFFSET_SHIFT | reg as u32)
+}
+
+/// Returns the number of a register, as stored in the binary
+fn reg_bits(w: &Word) -> u32 {
+    Into::<u8>::into(w.get_reg().unwrap()) as u32
+}
+
 impl Line {
-    fn get_binary_instruction_op_code<'a>(
+    /// Encode the operands of an instruction accessing the memory, the register field holds the register receiving the value loaded, or the base register of the address stored
+    fn get_binary_memory_access(
+        labels: &HashMap<String, u64>,
+        load: bool,
+        first: &Word,
+        second: &Word,
+        flag_shift: u32,
+    ) -> SyntaxResultKind<u32> {
+        Ok(if load {
+            reg_bits(first) << DST_SHIFT
+                | match second.get_address(labels)? {
+                    (Some(base), offset) => {
+                        inj_reg_with_offset(base.into(), offset, true, flag_shift)?
+                    }
+                    (None, address) => 1 << flag_shift | encode_address(address, flag_shift)?,
+                }
+        } else {
+            let (base, offset) = first.get_address(labels)?;
+            match (base, second.get_reg()) {
+                (_, Some(src)) => {
+                    (base.map_or(ABSOLUTE_BASE, |base| base.into()) as u32) << DST_SHIFT
+                        | inj_reg_with_offset(src.into(), offset, base.is_some(), flag_shift)?
+                }
+                (Some(base), None) if offset == 0 => {
+                    (Into::<u8>::into(base) as u32) << DST_SHIFT
+                        | inj_reg_or_imm!(second, labels, flag_shift)
+                }
+                _ => return Err(SyntaxErrorKind::ImmediateWithOffset),
+            }
+        })
+    }
+
+    fn get_binary_instruction_op_code(
         &self,
         labels: &HashMap<String, u64>,
         op_code: OpCode,
-        rest_of_line: Vec<&'a Word>,
-    ) -> Vec<u8> {
-        let mut instr: u32 = (Into::<u8>::into(op_code) as u32) << 27;
-        instr |= match rest_of_line.len() {
-            1 => match op_code {
-                OpCode::JMP(jcode) => {
-                    (Into::<u8>::into(jcode) as u32) << 24
-                        | inj_reg_or_imm!(rest_of_line[0], labels, 23)
+        rest_of_line: Vec<&Word>,
+    ) -> SyntaxResultKind<Vec<u8>> {
+        let form = op_code.form(&rest_of_line);
+        // The condition or the width of the op code
+        let parameter = op_code.parameter().unwrap_or(0) as u32;
+        // The operands of an extended instruction are laid out under its code
+        let (mut instr, shift) = form.code.bits();
+        instr |= match form.layout {
+            Layout::Nothing => 0,
+            Layout::Single => inj_reg_or_imm!(rest_of_line[0], labels, 26 - shift),
+            Layout::Once => 1 << (26 - shift) | 1,
+            Layout::Jump => {
+                parameter << JCODE_SHIFT | inj_reg_or_imm!(rest_of_line[0], labels, JCODE_SHIFT - 1)
+            }
+            Layout::Double => {
+                reg_bits(rest_of_line[0]) << (DST_SHIFT - shift)
+                    | inj_reg_or_imm!(rest_of_line[1], labels, DST_SHIFT - 1 - shift)
+            }
+            Layout::InPlace => {
+                let reg = reg_bits(rest_of_line[0]);
+                reg << (DST_SHIFT - shift) | reg
+            }
+            Layout::Triple => {
+                reg_bits(rest_of_line[0]) << DST_SHIFT
+                    | reg_bits(rest_of_line[1]) << SRC_SHIFT
+                    | inj_reg_or_imm!(rest_of_line[2], labels, ALU_SHIFT - 1)
+            }
+            Layout::Load | Layout::Store => Self::get_binary_memory_access(
+                labels,
+                form.layout == Layout::Load,
+                rest_of_line[0],
+                rest_of_line[1],
+                DST_SHIFT - 1,
+            )?,
+            Layout::SizedLoad | Layout::SizedStore => {
+                parameter << WIDTH_SHIFT
+                    | Self::get_binary_memory_access(
+                        labels,
+                        form.layout == Layout::SizedLoad,
+                        rest_of_line[0],
+                        rest_of_line[1],
+                        WIDTH_SHIFT - 1,
+                    )?
+            }
+            Layout::CondMove => {
+                parameter << JCODE_SHIFT
+                    | reg_bits(rest_of_line[0]) << COND_DST_SHIFT
+                    | inj_reg_or_imm!(rest_of_line[1], labels, SET_BIT - 1)
+            }
+            Layout::CondSet => {
+                parameter << JCODE_SHIFT
+                    | reg_bits(rest_of_line[0]) << COND_DST_SHIFT
+                    | 1 << SET_BIT
+            }
+            // The bit of each register to clear is set
+            Layout::Mask => rest_of_line
+                .iter()
+                .fold(0, |mask, w| mask | 1 << reg_bits(w)),
+        };
+        Ok(instr.to_be_bytes().to_vec())
+    }
+
+    fn get_binary_directive(
+        &self,
+        labels: &HashMap<String, u64>,
+        directive: Directive,
+        rest_of_line: Vec<&Word>,
+    ) -> SyntaxResultKind<Vec<u8>> {
+        let mut res = Vec::new();
+        match directive {
+            Directive::Byte | Directive::Half | Directive::Word => {
+                for w in rest_of_line {
+                    let value = w.get_value(labels)?;
+                    if !directive.fits(value) {
+                        return Err(SyntaxErrorKind::ValueOutOfRange(value));
+                    }
+                    res.extend_from_slice(
+                        &(value as u32).to_be_bytes()[4 - directive.item_size()..],
+                    )
                 }
-                _ => inj_reg_or_imm!(rest_of_line[0], labels, 26),
+            }
+            Directive::Zero => res.resize(self.directive_size(directive, &rest_of_line), 0),
+            Directive::Ascii | Directive::Asciz => {
+                for w in rest_of_line {
+                    res.extend_from_slice(w.get_str().unwrap().as_bytes());
+                    if directive == Directive::Asciz {
+                        res.push(0)
+                    }
+                }
+            }
+            Directive::Align | Directive::Balign => {
+                let fill = rest_of_line
+                    .get(1)
+                    .map_or(WordContent::Number(0), |w| w.content.clone());
+                res = padding(
+                    self.address,
+                    self.directive_size(directive, &rest_of_line),
+                    &fill,
+                )
+            }
+            Directive::Section
+            | Directive::Equ
+            | Directive::Set
+            | Directive::Enum
+            | Directive::EndEnum
+            | Directive::Include
+            | Directive::Once
+            | Directive::Org
+            | Directive::Entry
+            | Directive::If
+            | Directive::Ifdef
+            | Directive::Ifndef
+            | Directive::Else
+            | Directive::Endif
+            | Directive::Struct
+            | Directive::Ends
+            | Directive::Assert
+            | Directive::Error
+            | Directive::Warning
+            | Directive::Print => (),
+            Directive::Incbin => res = self.incbin.clone(),
+        }
+        Ok(res)
+    }
+
+    /// Returns the number of bytes emitted by the directive with the given operands, alignment depends on the address of the line
+    fn directive_size(&self, directive: Directive, rest_of_line: &[&Word]) -> usize {
+        match directive {
+            Directive::Byte | Directive::Half | Directive::Word => {
+                rest_of_line.len() * directive.item_size()
+            }
+            Directive::Zero => match rest_of_line[0].content {
+                WordContent::Number(x) => x as usize,
+                _ => unreachable!(),
             },
-            2 => {
-                (Into::<u8>::into(rest_of_line[0].get_reg().unwrap()) as u32) << 24
-                    | inj_reg_or_imm!(rest_of_line[1], labels, 23)
+            Directive::Ascii | Directive::Asciz => rest_of_line
+                .iter()
+                .map(|w| w.get_str().unwrap().len() + (directive == Directive::Asciz) as usize)
+                .sum(),
+            Directive::Align | Directive::Balign => {
+                (align_up(self.address, directive.alignment(rest_of_line)) - self.address) as usize
             }
-            _ => 0,
-        };
-        println!("{instr:b}");
-        let res = instr.to_be_bytes().to_vec();
-        res
+            Directive::Section
+            | Directive::Equ
+            | Directive::Set
+            | Directive::Enum
+            | Directive::EndEnum
+            | Directive::Include
+            | Directive::Once
+            | Directive::Org
+            | Directive::Entry
+            | Directive::If
+            | Directive::Ifdef
+            | Directive::Ifndef
+            | Directive::Else
+            | Directive::Endif
+            | Directive::Struct
+            | Directive::Ends
+            | Directive::Assert
+            | Directive::Error
+            | Directive::Warning
+            | Directive::Print => 0,
+            Directive::Incbin => self.incbin.len(),
+        }
     }
 
     fn skip_labels_decl<'a>(
@@ -190,34 +533,422 @@ impl Line {
         )
     }
 
-    pub fn get_binary_instruction(&self, labels: &HashMap<String, u64>) -> Vec<u8> {
+    pub fn get_binary_instruction(
+        &self,
+        labels: &HashMap<String, u64>,
+    ) -> SyntaxResultKind<Vec<u8>> {
         let mut words = self.instruction.iter();
         let word = match Self::skip_labels_decl(&mut words).0 {
             Some(w) => w,
-            None => return Vec::new(),
+            None => return Ok(Vec::new()),
         };
 
-        if let Some(s) = word.get_str() {
-            s.as_bytes().to_vec()
+        Ok(if let Some(s) = word.get_str() {
+            let mut res = s.as_bytes().to_vec();
+            res.push(0);
+            res
+        } else if let Some(directive) = word.get_directive() {
+            self.get_binary_directive(labels, directive, words.collect())?
         } else {
             self.get_binary_instruction_op_code(
                 labels,
                 word.get_op_code().unwrap(),
                 words.collect(),
-            )
-        }
+            )?
+        })
     }
 
-    /// This function returns all labels present on the line and the size of the line in bit
+    /// This function returns all labels present on the line and the size of the line in bytes
     pub fn get_line_info(&self) -> (Vec<String>, usize) {
-        let (word, labels) = Self::skip_labels_decl(&mut self.instruction.iter());
+        let mut words = self.instruction.iter();
+        let (word, labels) = Self::skip_labels_decl(&mut words);
         (
             labels,
             match word {
-                Some(w) if w.is_str() => w.get_str().unwrap().as_bytes().len(),
+                Some(w) if w.is_str() => w.get_str().unwrap().len() + 1,
+                Some(w) if w.get_directive().is_some() => {
+                    self.directive_size(w.get_directive().unwrap(), &words.collect::<Vec<_>>())
+                }
                 Some(_) => 4,
                 None => 0,
             },
         )
     }
+
+    /// Returns true if the line contains an instruction, the labels declaration are ignored
+    pub fn is_instruction(&self) -> bool {
+        let word = Self::skip_labels_decl(&mut self.instruction.iter()).0;
+        word.is_some_and(|w| w.get_op_code().is_some())
+    }
+
+    /// Returns the directive of the line if any
+    pub fn get_directive(&self) -> Option<Directive> {
+        Self::skip_labels_decl(&mut self.instruction.iter())
+            .0?
+            .get_directive()
+    }
+
+    /// Returns the name and the value of the constant declared by the line if any
+    pub fn get_constant_definition(&self) -> Option<(&String, i32)> {
+        let mut words = self.instruction.iter();
+        match Self::skip_labels_decl(&mut words).0?.get_directive()? {
+            Directive::Equ | Directive::Set => {
+                match (&words.next()?.content, &words.next()?.content) {
+                    (WordContent::Label(name), WordContent::Number(x)) => Some((name, *x)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the directive of the line with its operands if it is a diagnostic directive
+    pub fn get_diagnostic(&self) -> Option<(Directive, Vec<&Word>)> {
+        let mut words = self.instruction.iter();
+        let directive = Self::skip_labels_decl(&mut words).0?.get_directive()?;
+        directive
+            .is_diagnostic()
+            .then(|| (directive, words.collect()))
+    }
+
+    /// Returns the name given to a .struct directive
+    pub fn get_struct_name(&self) -> Option<&String> {
+        let mut words = self.instruction.iter();
+        match Self::skip_labels_decl(&mut words).0?.get_directive()? {
+            Directive::Struct => match &words.next()?.content {
+                WordContent::Label(name) => Some(name),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the truth of the condition opening a conditional block, the resolver tells if a constant is declared
+    pub fn get_condition(&self, is_declared: impl Fn(&str) -> bool) -> Option<bool> {
+        let mut words = self.instruction.iter();
+        let directive = Self::skip_labels_decl(&mut words).0?.get_directive()?;
+        match (directive, &words.next()?.content) {
+            (Directive::If, WordContent::Number(x)) => Some(*x != 0),
+            (Directive::Ifdef, WordContent::Label(name)) => Some(is_declared(name)),
+            (Directive::Ifndef, WordContent::Label(name)) => Some(!is_declared(name)),
+            _ => None,
+        }
+    }
+
+    /// Returns the first value of the enumeration started by the line if it is an .enum directive
+    pub fn get_enum_start(&self) -> Option<i32> {
+        let mut words = self.instruction.iter();
+        match Self::skip_labels_decl(&mut words).0?.get_directive()? {
+            Directive::Enum => match words.next() {
+                Some(w) => match w.content {
+                    WordContent::Number(x) => Some(x),
+                    _ => None,
+                },
+                None => Some(0),
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the file to include if the line is an .include directive
+    pub fn get_include(&self) -> Option<&String> {
+        let mut words = self.instruction.iter();
+        match Self::skip_labels_decl(&mut words).0?.get_directive()? {
+            Directive::Include => words.next()?.get_str(),
+            _ => None,
+        }
+    }
+
+    /// Returns the file, the offset and the length eventually given to an .incbin directive
+    pub fn get_incbin(&self) -> Option<(&String, usize, Option<usize>)> {
+        let mut words = self.instruction.iter();
+        match Self::skip_labels_decl(&mut words).0?.get_directive()? {
+            Directive::Incbin => {
+                let file = words.next()?.get_str()?;
+                let mut numbers = words.map(|w| match w.content {
+                    WordContent::Number(x) => x as usize,
+                    _ => unreachable!(),
+                });
+                Some((file, numbers.next().unwrap_or(0), numbers.next()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the labels declared on the line
+    pub fn get_labels_decl(&self) -> Vec<String> {
+        Self::skip_labels_decl(&mut self.instruction.iter()).1
+    }
+
+    /// Returns the section selected by the line if it is a section directive
+    pub fn get_new_section(&self) -> Option<Section> {
+        let mut words = self.instruction.iter();
+        match Self::skip_labels_decl(&mut words).0?.get_directive()? {
+            Directive::Section => words.next()?.get_section(),
+            _ => None,
+        }
+    }
+
+    /// Returns the label given to an .entry directive
+    pub fn get_entry(&self) -> Option<&String> {
+        let mut words = self.instruction.iter();
+        match Self::skip_labels_decl(&mut words).0?.get_directive()? {
+            Directive::Entry => match &words.next()?.content {
+                WordContent::Label(lab) => Some(lab),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the address given to an .org directive
+    pub fn get_org(&self) -> Option<u64> {
+        let mut words = self.instruction.iter();
+        match Self::skip_labels_decl(&mut words).0?.get_directive()? {
+            Directive::Org => match words.next()?.content {
+                WordContent::Number(x) => Some(x as u64),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns true if the line does not emit anything else than null bytes
+    pub fn is_reservation(&self) -> bool {
+        match Self::skip_labels_decl(&mut self.instruction.iter()).0 {
+            Some(w) => w.get_directive().is_some_and(|d| d.is_reservation()),
+            None => true,
+        }
+    }
+
+    /// Returns true if the line only contains labels declaration
+    pub fn is_empty(&self) -> bool {
+        Self::skip_labels_decl(&mut self.instruction.iter())
+            .0
+            .is_none()
+    }
+
+    /// Returns the number given to an INT instruction on this line, if it is not a register
+    pub fn get_interrupt(&self) -> Option<&Word> {
+        let mut words = self.instruction.iter();
+        match Self::skip_labels_decl(&mut words).0?.get_op_code()? {
+            OpCode::INT => words.next().filter(|w| w.is_imm()),
+            _ => None,
+        }
+    }
+
+    /// Returns the label used as destination of a jump or a call on this line if any
+    pub fn get_jump_target(&self) -> Option<&String> {
+        let mut words = self.instruction.iter();
+        match Self::skip_labels_decl(&mut words).0?.get_op_code()? {
+            OpCode::JMP(_) | OpCode::CALL => match &words.next()?.content {
+                WordContent::Label(lab) => Some(lab),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns all the labels used on the line, the labels declaration are ignored
+    pub fn get_used_labels(&self) -> Vec<&String> {
+        let mut words = self.instruction.iter();
+        let directive = Self::skip_labels_decl(&mut words)
+            .0
+            .and_then(|w| w.get_directive());
+        words
+            .enumerate()
+            .skip(directive.map_or(0, |d| d.name_operands()))
+            .flat_map(|(i, w)| match &w.content {
+                WordContent::Label(lab)
+                    if !(i == 1
+                        && lab == NOP_FILL
+                        && matches!(directive, Some(Directive::Align | Directive::Balign))) =>
+                {
+                    vec![lab]
+                }
+                WordContent::Expr(expr) => expr.symbols(),
+                WordContent::Memory(_, offset) => match &offset.content {
+                    WordContent::Label(lab) => vec![lab],
+                    WordContent::Expr(expr) => expr.symbols(),
+                    _ => Vec::new(),
+                },
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::tests::{try_assemble, words};
+
+    #[test]
+    fn immediate_values_are_signed() {
+        assert_eq!(encode_imm(0, 22).unwrap(), 0);
+        assert_eq!(encode_imm(-1, 22).unwrap(), 0x3FFFFF);
+        assert_eq!(encode_imm(0x1FFFFF, 22).unwrap(), 0x1FFFFF);
+        assert_eq!(encode_imm(-0x200000, 22).unwrap(), 0x200000);
+        // -1 is the only way to write 0x3FFFFF
+        assert!(encode_imm(0x3FFFFF, 22).is_err());
+        assert!(encode_imm(0x200000, 22).is_err());
+        assert!(encode_imm(-0x200001, 22).is_err());
+    }
+
+    #[test]
+    fn absolute_addresses_are_unsigned() {
+        assert_eq!(encode_address(0x3FFFFF, 22).unwrap(), 0x3FFFFF);
+        assert!(encode_address(0x400000, 22).is_err());
+        assert!(encode_address(-1, 22).is_err());
+    }
+
+    #[test]
+    fn offsets_from_a_register_are_signed() {
+        assert_eq!(inj_reg_with_offset(2, -4, true, 22).unwrap(), 0x3FFFC2);
+        assert!(inj_reg_with_offset(2, 0x20000, true, 22).is_err());
+        assert_eq!(
+            inj_reg_with_offset(2, 0x20000, false, 22).unwrap(),
+            0x200002
+        );
+    }
+
+    /// Keep the given number of low bits of a signed value, as stored in a field
+    fn signed(x: i32, bits: u32) -> u32 {
+        x as u32 & ((1 << bits) - 1)
+    }
+
+    #[test]
+    fn instructions_follow_the_readme_layout() {
+        // The expected words are built field by field from the layout of the Binary Format section of the README
+        let cases: &[(&str, u32)] = &[
+            // Memory operands
+            ("rd r0, [r1 + 8]", 19 << 27 | 8 << 4 | 1),
+            ("rd r2, [64]", 19 << 27 | 2 << 23 | 1 << 22 | 64),
+            ("rd r2, r1", 19 << 27 | 2 << 23 | 1),
+            ("rd r2, [r1]", 19 << 27 | 2 << 23 | 1),
+            (
+                "wr [r2 - 4], r3",
+                20 << 27 | 2 << 23 | signed(-4, 18) << 4 | 3,
+            ),
+            ("wr [64], r3", 20 << 27 | 15 << 23 | 64 << 4 | 3),
+            ("wr [r2], 7", 20 << 27 | 2 << 23 | 1 << 22 | 7),
+            // Byte and half word accesses
+            ("rdb r0, [r1 + 8]", 27 << 27 | 8 << 4 | 1),
+            (
+                "rdhs r2, [r1 - 2]",
+                27 << 27 | 2 << 23 | 3 << 21 | signed(-2, 16) << 4 | 1,
+            ),
+            ("rdbs r0, [64]", 27 << 27 | 1 << 21 | 1 << 20 | 64),
+            (
+                "wrh [r2 + 2], r3",
+                28 << 27 | 2 << 23 | 2 << 21 | 2 << 4 | 3,
+            ),
+            ("wrb [r2], 7", 28 << 27 | 2 << 23 | 1 << 20 | 7),
+            // Jumps
+            ("jne r1", 12 << 27 | 6 << 23 | 1),
+            ("jae 8", 12 << 27 | 10 << 23 | 1 << 22 | 8),
+            ("jb -4", 12 << 27 | 7 << 23 | 1 << 22 | signed(-4, 22)),
+            // Special registers
+            ("mov rpc, r1", 18 << 27 | 8 << 23 | 1),
+            ("cmp rcond, 0", 21 << 27 | 9 << 23 | 1 << 22),
+            ("mov r2, rcond", 18 << 27 | 2 << 23 | 9),
+            // Three operands
+            ("add r2, r0, r1", 29 << 27 | 2 << 23 | 1),
+            ("sub r2, r0, 5", 29 << 27 | 2 << 23 | 2 << 15 | 1 << 14 | 5),
+            (
+                "xor r3, r4, -1",
+                29 << 27 | 3 << 23 | 4 << 19 | 10 << 15 | 1 << 14 | signed(-1, 14),
+            ),
+            // Stack instructions
+            ("drop", 31 << 27 | 1 << 20 | 1),
+            ("drop r0", 31 << 27),
+            ("drop 3", 31 << 27 | 1 << 20 | 3),
+            ("over", 31 << 27 | 1 << 21),
+            ("rot", 31 << 27 | 2 << 21),
+            ("pick 2", 31 << 27 | 3 << 21 | 1 << 20 | 2),
+            // Extended arithmetic
+            ("rol r1, 3", 31 << 27 | 4 << 21 | 1 << 17 | 1 << 16 | 3),
+            ("sar r2, r3", 31 << 27 | 6 << 21 | 2 << 17 | 3),
+            ("mulhu r0, r1", 31 << 27 | 10 << 21 | 1),
+            ("clz r4, r5", 31 << 27 | 12 << 21 | 4 << 17 | 5),
+            // Register forms of neg, not and clear
+            ("neg", 5 << 27),
+            ("neg r1", 31 << 27 | 13 << 21 | 1),
+            ("not r2", 11 << 27 | 2 << 23 | 2),
+            ("not r2, r3", 11 << 27 | 2 << 23 | 3),
+            ("clear", 22 << 27),
+            (
+                "clear r0, r3, rpc",
+                31 << 27 | 14 << 21 | 1 << 8 | 1 << 3 | 1,
+            ),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(
+                words(&format!("{source}\n")),
+                [*expected],
+                "{source}: {:#010X}",
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_operands_are_rejected() {
+        for source in [
+            "wr [r2 + 4], 7\n",
+            "wr [64], 7\n",
+            "rd r0, [r1 + 131072]\n",
+            "rd r0, [r1 * 2]\n",
+            "rdb r0, [r1 + 32768]\n",
+            "wrbs [r2], r3\n",
+            "mov rcond, 1\n",
+            "pop rcond\n",
+            "sub r2, r0, 8192\n",
+            "add r2, 1, r0\n",
+            "over r0\n",
+            "divu r0, 32768\n",
+            "popcnt r0, r1, r2\n",
+            "clear rcond\n",
+            "neg 1\n",
+        ] {
+            assert!(try_assemble(source).is_err(), "{source}");
+        }
+    }
+
+    #[test]
+    fn conditional_moves_and_sets() {
+        // The conditions in the order of their number in the README
+        let conditions = ["e", "h", "l", "le", "he", "ne", "b", "a", "be", "ae"];
+        for (cond, name) in (1..).zip(conditions) {
+            for (dst, reg) in [(0, "r0"), (7, "r7"), (8, "rpc")] {
+                let [set] = words(&format!("set{name} {reg}\n"))[..] else {
+                    panic!("set{name} {reg}")
+                };
+                assert_eq!(set >> 27, 30, "set{name} {reg}");
+                assert_eq!(set >> 23 & 0xF, cond, "set{name} {reg}");
+                assert_eq!(set >> 19 & 0xF, dst, "set{name} {reg}");
+                assert_eq!(set >> 18 & 1, 1, "set{name} {reg}");
+                assert_eq!(set & 0x3FFFF, 0, "set{name} {reg}");
+
+                let [mov] = words(&format!("cmov{name} {reg}, r5\n"))[..] else {
+                    panic!("cmov{name} {reg}")
+                };
+                assert_eq!(mov >> 27, 30, "cmov{name} {reg}");
+                assert_eq!(mov >> 23 & 0xF, cond, "cmov{name} {reg}");
+                assert_eq!(mov >> 19 & 0xF, dst, "cmov{name} {reg}");
+                assert_eq!(mov >> 18 & 1, 0, "cmov{name} {reg}");
+                // The flag at bit 17 is clear for a register source, stored on bits 3 to 0
+                assert_eq!(mov & 0x3FFFF, 5, "cmov{name} {reg}");
+            }
+        }
+        // An immediate source sets the flag and is stored signed on bits 16 to 0
+        let [mov] = words("cmovae r2, -1\n")[..] else {
+            panic!("cmovae r2, -1")
+        };
+        assert_eq!(mov >> 17 & 1, 1);
+        assert_eq!(mov & 0x1FFFF, 0x1FFFF);
+        assert!(try_assemble("cmovae r2, 65536\n").is_err());
+        assert!(try_assemble("setne rcond\n").is_err());
+        assert!(try_assemble("sete 1\n").is_err());
+    }
 }
@@ -0,0 +1,56 @@
+use std::{
+    fmt::{Debug, Error as FmtErr, Formatter},
+    path::PathBuf,
+    rc::Rc,
+};
+
+/// How a line has been brought by the line of its parent location
+#[derive(Clone)]
+pub enum Origin {
+    /// The line is in a file brought by an .include directive
+    Include,
+    /// The line is in the body of the given macro, expanded by an invocation
+    Macro(Rc<str>),
+    /// The line is in the body of a repetition block, at the given iteration
+    Repetition(usize),
+}
+
+/// The position of a line in the sources
+#[derive(Clone)]
+pub struct Location {
+    pub file: Rc<PathBuf>,
+    pub line: usize,
+    /// The location of the line that brought this one, the .include line or the macro invocation, None for the main file
+    pub parent: Option<Rc<Location>>,
+    pub origin: Origin,
+}
+
+impl Location {
+    /// Returns an iterator over the location and all the lines that brought it, from the deepest one
+    pub fn chain(&self) -> impl Iterator<Item = &Location> {
+        std::iter::successors(Some(self), |l| l.parent.as_deref())
+    }
+
+    /// Returns the number of macro expansions and repetitions the line is in
+    pub fn expansion_depth(&self) -> usize {
+        self.chain()
+            .filter(|l| !matches!(l.origin, Origin::Include))
+            .count()
+    }
+}
+
+impl Debug for Origin {
+    fn fmt(&self, f: &mut Formatter) -> core::result::Result<(), FmtErr> {
+        match self {
+            Self::Include => write!(f, "included from"),
+            Self::Macro(name) => write!(f, "in the macro \"{name}\" expanded from"),
+            Self::Repetition(i) => write!(f, "in the iteration {i} of the block from"),
+        }
+    }
+}
+
+impl Debug for Location {
+    fn fmt(&self, f: &mut Formatter) -> core::result::Result<(), FmtErr> {
+        write!(f, "{} line {}", self.file.display(), self.line)
+    }
+}
@@ -0,0 +1,493 @@
+use std::rc::Rc;
+
+use super::{
+    errors::{is_valid_label_name, SyntaxErrorKind, SyntaxResultKind},
+    expr::OPERATOR_CHARS,
+    location::{Location, Origin},
+    op_codes::OpCode,
+    COMMENT_CHAR,
+};
+
+pub const MACRO_START: &str = ".macro";
+pub const MACRO_END: &str = ".endm";
+pub const REPT_START: &str = ".rept";
+pub const IRP_START: &str = ".irp";
+pub const REPT_END: &str = ".endr";
+
+/// The suffix of a parameter receiving all the remaining arguments
+const VARARG: &str = ":vararg";
+
+/// Split a line of the sources in the label declarations at its beginning, its first word and the rest of the line, without the comment
+pub fn split_statement(text: &str) -> (&str, Option<&str>, &str) {
+    let mut in_quote = None;
+    let end = text
+        .char_indices()
+        .find(|&(_, c)| {
+            match in_quote {
+                Some(q) if c == q => in_quote = None,
+                None if c == '"' || c == '\'' => in_quote = Some(c),
+                _ => (),
+            }
+            in_quote.is_none() && c == COMMENT_CHAR
+        })
+        .map_or(text.len(), |(i, _)| i);
+    let code = &text[..end];
+    let mut prefix_end = 0;
+    loop {
+        let rest = &code[prefix_end..];
+        let start = prefix_end + rest.len() - rest.trim_start().len();
+        let word_end = code[start..]
+            .find(char::is_whitespace)
+            .map_or(code.len(), |i| start + i);
+        let word = &code[start..word_end];
+        if word.is_empty() {
+            return (&code[..prefix_end], None, "");
+        }
+        if !word.ends_with(':') {
+            return (&code[..prefix_end], Some(word), &code[word_end..]);
+        }
+        prefix_end = word_end;
+    }
+}
+
+/// Split the arguments of a macro invocation on the commas that are not between quotes or parenthesis
+pub fn split_arguments(text: &str) -> Vec<String> {
+    let mut res = Vec::new();
+    let mut current = String::new();
+    let mut in_quote = None;
+    let mut depth = 0;
+    for c in text.chars() {
+        match (in_quote, c) {
+            (Some(q), _) if c == q => in_quote = None,
+            (Some(_), _) => (),
+            (None, '"' | '\'') => in_quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                res.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => (),
+        }
+        current.push(c);
+    }
+    if !current.trim().is_empty() || !res.is_empty() {
+        res.push(current.trim().to_string());
+    }
+    res
+}
+
+/// Returns true if the char can't be a part of a label name
+fn is_boundary(c: char) -> bool {
+    c.is_whitespace() || ",:[]".contains(c) || OPERATOR_CHARS.contains(c)
+}
+
+/// Apply the function to each word of the line that is not between quotes, the word is replaced by the returned value if any
+fn replace_words(text: &str, f: impl Fn(&str) -> Option<String>) -> String {
+    let mut res = String::new();
+    let mut word = String::new();
+    let mut in_quote = None;
+    let flush = |word: &mut String, res: &mut String| {
+        match f(word) {
+            Some(value) => res.push_str(&value),
+            None => res.push_str(word),
+        }
+        word.clear();
+    };
+    for c in text.chars() {
+        match in_quote {
+            Some(q) => {
+                if c == q {
+                    in_quote = None
+                }
+                res.push(c);
+            }
+            // The parenthesis of \() belong to the word
+            None if (c == '(' && word.ends_with('\\')) || (c == ')' && word.ends_with("\\(")) => {
+                word.push(c)
+            }
+            None if c == '"' || c == '\'' || is_boundary(c) => {
+                flush(&mut word, &mut res);
+                if c != '"' && c != '\'' {
+                    res.push(c);
+                    continue;
+                }
+                in_quote = Some(c);
+                res.push(c);
+            }
+            None => word.push(c),
+        }
+    }
+    flush(&mut word, &mut res);
+    res
+}
+
+struct Param {
+    name: String,
+    default: Option<String>,
+    /// If set, the parameter receives all the remaining arguments
+    variadic: bool,
+}
+
+impl TryFrom<&str> for Param {
+    type Error = SyntaxErrorKind;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        let (spec, variadic) = match s.strip_suffix(VARARG) {
+            Some(spec) => (spec, true),
+            None => (s, false),
+        };
+        let (name, default) = match spec.split_once('=') {
+            Some((name, default)) if !variadic => (name.trim(), Some(default.trim().to_string())),
+            _ => (spec, None),
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(SyntaxErrorKind::InvalidMacroParameter(s.to_string()));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            default,
+            variadic,
+        })
+    }
+}
+
+/// The names hidden in the body of a nested repetition block, they belong to the block and are substituted when it is expanded
+#[derive(Default)]
+struct Hidden {
+    /// Set inside a nested block, its counter hides the one of the expansion and its \() are kept
+    nested: bool,
+    /// The parameters of the nested .irp blocks
+    params: Vec<String>,
+}
+
+/// Replace each \param of the word by the value of the parameter, \# by the iteration counter and \() by nothing, to stick a parameter to the following chars. The hidden names are left as they are.
+fn substitute_params(
+    word: &str,
+    params: &[Param],
+    values: &[String],
+    counter: usize,
+    hidden: &Hidden,
+) -> String {
+    let mut res = String::new();
+    let mut rest = word;
+    while let Some(i) = rest.find('\\') {
+        res.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        if let Some(after) = rest.strip_prefix("()") {
+            if hidden.nested {
+                res.push_str("\\()");
+            }
+            rest = after;
+            continue;
+        }
+        if let Some(after) = rest.strip_prefix('#') {
+            match hidden.nested {
+                true => res.push_str("\\#"),
+                false => res.push_str(&counter.to_string()),
+            }
+            rest = after;
+            continue;
+        }
+        let end = rest
+            .find(|c: char| !c.is_alphanumeric() && c != '_')
+            .unwrap_or(rest.len());
+        match params
+            .iter()
+            .position(|p| p.name == rest[..end] && !hidden.params.contains(&p.name))
+        {
+            Some(p) => res.push_str(&values[p]),
+            None => {
+                res.push('\\');
+                res.push_str(&rest[..end]);
+            }
+        }
+        rest = &rest[end..];
+    }
+    res.push_str(rest);
+    res
+}
+
+/// A macro defined by `.macro name arg1, arg2=default, rest:vararg`, the parameters are used in the body as \arg1. A repetition block is an anonymous macro expanded once per iteration.
+pub struct Macro {
+    pub name: Rc<str>,
+    params: Vec<Param>,
+    /// The lines of the body with their location in the sources
+    body: Vec<(String, Location)>,
+    /// The labels declared in the body, they get a unique name on each expansion
+    local_labels: Vec<String>,
+}
+
+impl Macro {
+    /// Build a macro from the text following the .macro directive, the body is pushed later
+    pub fn new(header: &str) -> SyntaxResultKind<Self> {
+        let header = header.trim();
+        let (name, params) = header
+            .split_once(|c: char| c.is_whitespace() || c == ',')
+            .unwrap_or((header, ""));
+        if !is_valid_label_name(name) || OpCode::try_from(name).is_ok() {
+            return Err(SyntaxErrorKind::InvalidMacroName(name.to_string()));
+        }
+        let params = params
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(Param::try_from)
+            .collect::<SyntaxResultKind<Vec<_>>>()?;
+        for (i, p) in params.iter().enumerate() {
+            if (p.variadic && i + 1 != params.len())
+                || params[..i].iter().any(|other| other.name == p.name)
+            {
+                return Err(SyntaxErrorKind::InvalidMacroParameter(p.name.clone()));
+            }
+        }
+        Ok(Self {
+            name: name.into(),
+            params,
+            body: Vec::new(),
+            local_labels: Vec::new(),
+        })
+    }
+
+    /// Build the body of a repetition block, with the parameter of an .irp block if any
+    pub fn repetition(name: &str, param: Option<&str>) -> SyntaxResultKind<Self> {
+        Ok(Self {
+            name: name.into(),
+            params: param
+                .map(Param::try_from)
+                .into_iter()
+                .collect::<Result<_, _>>()?,
+            body: Vec::new(),
+            local_labels: Vec::new(),
+        })
+    }
+
+    pub fn push_line(&mut self, text: &str, location: Location) {
+        let (labels, _, _) = split_statement(text);
+        self.local_labels.extend(
+            labels
+                .split_whitespace()
+                .map(|l| l.trim_end_matches(':').to_string()),
+        );
+        self.body.push((text.to_string(), location));
+    }
+
+    /// Returns the lines of the body with the given arguments, each one located in the body and expanded from the invocation. The number identifies the expansion, it is used to build the unique names of the local labels.
+    pub fn expand(
+        &self,
+        args: &str,
+        n: usize,
+        invocation: &Rc<Location>,
+    ) -> SyntaxResultKind<Vec<(String, Location)>> {
+        let mut args = split_arguments(args).into_iter();
+        let mut values = Vec::new();
+        for p in &self.params {
+            let value = if p.variadic {
+                args.by_ref().collect::<Vec<_>>().join(", ")
+            } else {
+                match (args.next(), &p.default) {
+                    (Some(arg), _) if !arg.is_empty() => arg,
+                    (_, Some(default)) => default.clone(),
+                    _ => {
+                        return Err(SyntaxErrorKind::MissingArgument(
+                            self.name.to_string(),
+                            p.name.clone(),
+                        ))
+                    }
+                }
+            };
+            values.push(value);
+        }
+        if args.next().is_some() {
+            return Err(SyntaxErrorKind::TooManyArguments(self.name.to_string()));
+        }
+        Ok(self.instantiate(&values, 0, n, invocation, Origin::Macro(self.name.clone())))
+    }
+
+    /// Returns the lines of the iteration of a repetition block, an .irp block gives the value of its parameter
+    pub fn iterate(
+        &self,
+        value: Option<String>,
+        counter: usize,
+        n: usize,
+        block: &Rc<Location>,
+    ) -> Vec<(String, Location)> {
+        let values = value.into_iter().collect::<Vec<_>>();
+        self.instantiate(&values, counter, n, block, Origin::Repetition(counter))
+    }
+
+    fn instantiate(
+        &self,
+        values: &[String],
+        counter: usize,
+        n: usize,
+        parent: &Rc<Location>,
+        origin: Origin,
+    ) -> Vec<(String, Location)> {
+        // The parameters of the nested .irp blocks, None for a .rept block
+        let mut nested: Vec<Option<String>> = Vec::new();
+        self.body
+            .iter()
+            .map(|(text, location)| {
+                let (_, first_word, rest) = split_statement(text);
+                if first_word == Some(REPT_END) {
+                    nested.pop();
+                }
+                let hidden = Hidden {
+                    nested: !nested.is_empty(),
+                    params: nested.iter().flatten().cloned().collect(),
+                };
+                // The header of a nested block is substituted, its body is not
+                match first_word {
+                    Some(REPT_START) => nested.push(None),
+                    Some(IRP_START) => nested.push(Some(split_irp_header(rest).0.to_string())),
+                    _ => (),
+                }
+                let text = replace_words(text, |w| {
+                    if w.contains('\\') {
+                        Some(substitute_params(w, &self.params, values, counter, &hidden))
+                    } else if self.local_labels.iter().any(|l| l == w) {
+                        Some(format!("{w}@{n}"))
+                    } else {
+                        None
+                    }
+                });
+                let location = Location {
+                    parent: Some(parent.clone()),
+                    origin: origin.clone(),
+                    ..location.clone()
+                };
+                (text, location)
+            })
+            .collect()
+    }
+}
+
+/// Returns the values given to an .irp block, after the name of its parameter
+pub fn split_irp_header(header: &str) -> (&str, Vec<String>) {
+    let header = header.trim();
+    let (name, values) = header.split_once(',').unwrap_or((header, ""));
+    (name.trim(), split_arguments(values))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn location(line: usize) -> Location {
+        Location {
+            file: Rc::new(PathBuf::from("test.nzm")),
+            line,
+            parent: None,
+            origin: Origin::Include,
+        }
+    }
+
+    /// Build a repetition block from its body and returns the text of the given iteration
+    fn iteration(
+        param: Option<&str>,
+        body: &[&str],
+        value: Option<&str>,
+        counter: usize,
+    ) -> Vec<String> {
+        let mut block = Macro::repetition(REPT_START, param).unwrap();
+        for (i, line) in body.iter().enumerate() {
+            block.push_line(line, location(i + 1));
+        }
+        let parent = Rc::new(location(0));
+        block
+            .iterate(value.map(str::to_string), counter, 0, &parent)
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect()
+    }
+
+    #[test]
+    fn split_statement_separates_labels_and_comment() {
+        assert_eq!(
+            split_statement("a: b: mov r0, 1 ; comment"),
+            ("a: b:", Some("mov"), " r0, 1 ")
+        );
+        assert_eq!(split_statement("  ; only a comment"), ("", None, ""));
+    }
+
+    #[test]
+    fn split_arguments_keeps_quotes_and_parenthesis() {
+        assert_eq!(
+            split_arguments(r#"(1, 2), "a, b", c"#),
+            vec!["(1, 2)", r#""a, b""#, "c"]
+        );
+        assert!(split_arguments("  ").is_empty());
+    }
+
+    #[test]
+    fn counter_and_parameter_are_substituted() {
+        assert_eq!(
+            iteration(
+                Some("reg"),
+                &["handler_\\reg: add \\reg, \\#", "\\reg\\()_end:"],
+                Some("r1"),
+                2
+            ),
+            vec!["handler_r1: add r1, 2", "r1_end:"]
+        );
+    }
+
+    #[test]
+    fn nested_block_keeps_its_own_counter() {
+        let body = [
+            ".rept 2",
+            "    .byte \\#, \\x",
+            "    .byte \\y\\()0",
+            ".endr",
+            "    .byte \\#",
+        ];
+        assert_eq!(
+            iteration(Some("x"), &body, Some("7"), 1),
+            vec![
+                ".rept 2",
+                "    .byte \\#, 7",
+                "    .byte \\y\\()0",
+                ".endr",
+                "    .byte 1"
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_irp_hides_its_parameter() {
+        let body = [".irp x, \\x, 2", "    .byte \\x", ".endr", "    .byte \\x"];
+        assert_eq!(
+            iteration(Some("x"), &body, Some("5"), 0),
+            vec![".irp x, 5, 2", "    .byte \\x", ".endr", "    .byte 5"]
+        );
+    }
+
+    #[test]
+    fn macro_arguments_defaults_and_varargs() {
+        let mut def = Macro::new("m a, b=3, rest:vararg").unwrap();
+        def.push_line("    .byte \\a, \\b, \\rest", location(1));
+        let parent = Rc::new(location(0));
+        let expand = |args: &str| def.expand(args, 0, &parent).map(|lines| lines[0].0.clone());
+        assert_eq!(expand("1").unwrap(), "    .byte 1, 3, ");
+        assert_eq!(expand("1, 2, 4, 5").unwrap(), "    .byte 1, 2, 4, 5");
+        assert!(matches!(
+            expand(""),
+            Err(SyntaxErrorKind::MissingArgument(_, _))
+        ));
+    }
+
+    #[test]
+    fn local_labels_get_a_unique_name() {
+        let mut def = Macro::new("m").unwrap();
+        def.push_line("loop: jmp loop", location(1));
+        let parent = Rc::new(location(0));
+        assert_eq!(
+            def.expand("", 4, &parent).unwrap()[0].0,
+            "loop@4: jmp loop@4"
+        );
+    }
+}
@@ -1,22 +1,102 @@
+mod config;
+mod directives;
+mod disassembler;
 mod errors;
+mod expr;
+mod isa;
 mod line;
+mod location;
+mod macro_def;
 mod op_codes;
+mod pseudo;
 mod registers;
+mod syscalls;
 mod word;
 
 pub const COMMENT_CHAR: char = ';';
 
-use errors::{cast_result, SyntaxErrorKind, SyntaxResult};
-use line::Line;
+/// A conditional block being assembled
+struct Condition {
+    /// True if the lines of the current branch are assembled
+    active: bool,
+    /// True if a branch of the block has already been assembled
+    taken: bool,
+    /// True once the .else of the block has been found
+    has_else: bool,
+    /// The line of the directive opening the block
+    line: usize,
+}
+
+/// The kind of a block whose body is read before being assembled
+enum BlockKind {
+    Macro,
+    /// A .rept block with its number of iterations
+    Rept(usize),
+    /// An .irp block with the values of its parameter
+    Irp(Vec<String>),
+}
+
+impl BlockKind {
+    /// Returns the directives starting a block of the same kind, and the one ending it
+    fn delimiters(&self) -> (&'static [&'static str], &'static str) {
+        match self {
+            Self::Macro => (&[MACRO_START], MACRO_END),
+            _ => (&[REPT_START, IRP_START], REPT_END),
+        }
+    }
+}
+
+/// A macro or a repetition block whose body is being read
+struct Definition {
+    macro_def: Macro,
+    kind: BlockKind,
+    /// The number of blocks of the same kind started in the body and not yet ended
+    depth: usize,
+    /// The line of the directive starting the block
+    line: usize,
+    location: Location,
+}
+
+/// A structure whose fields are being declared
+struct Structure {
+    name: String,
+    /// The offset of the next field
+    offset: u64,
+    /// The line of the .struct directive
+    line: usize,
+}
+
+/// The maximum number of nested expansions, to stop a macro invoking itself endlessly
+const MAX_EXPANSION_DEPTH: usize = 64;
+
+pub use config::Config;
+use directives::{Directive, Section};
+pub use disassembler::print_disassembly;
+pub use errors::is_valid_label_name;
+use errors::{
+    cast_result, SyntaxErrorKind, SyntaxResult, SyntaxResultKind, SyntaxWarning, SyntaxWarningKind,
+};
+use expr::Expr;
+pub use isa::print_isa;
+use line::{align_up, Line};
+use location::{Location, Origin};
+use macro_def::{
+    split_irp_header, split_statement, Macro, IRP_START, MACRO_END, MACRO_START, REPT_END,
+    REPT_START,
+};
+use op_codes::FORMAT_VERSION;
+pub use syscalls::print_syscalls;
+use syscalls::{find_syscall, is_syscall_name, SYSCALLS};
 use word::{Word, WordBuilder, WordContent, WordRequest};
 
 use std::{
-    collections::HashMap,
-    fs::File,
-    io::{Error as IoError, Write},
+    collections::{HashMap, HashSet},
+    fs::{canonicalize, read_to_string},
+    path::{Path, PathBuf},
+    rc::Rc,
 };
 
-fn display_lines(lines: &Vec<Line>) {
+fn display_lines(lines: &[Line]) {
     println!(
         "Pure content:\n{}\n",
         lines
@@ -49,26 +129,84 @@ pub struct Assembler {
     current_line: Vec<Word>,
     /// Each element of the vector is an instruction composed of different word
     instructions: Vec<Line>,
+    /// The location in the sources of each line of instructions, followed by the one of the line being built
+    locations: Vec<Location>,
+    /// The file requested by the last .include directive, it is fed once its line is over
+    pending_include: Option<String>,
+    /// The files containing a .once directive, they are not included again
+    once_files: HashSet<PathBuf>,
     /// Link a label to its address, also used to verify the existence of labels when parsing the code
     labels: HashMap<String, u64>,
+    /// Link a constant to its value, the constants do not occupy any address and are replaced by their value as soon as the line is built
+    constants: HashMap<String, i32>,
+    /// The constants label.len declared by the .incbin lines, they can be used before their declaration
+    lengths: HashSet<String>,
+    /// The value of the next name if we are in an .enum block
+    enum_value: Option<i32>,
+    /// The section in which the lines are currently placed
+    section: Section,
+    /// The conditional blocks surrounding the current line, from the outermost one
+    conditions: Vec<Condition>,
+    /// The structure being declared, if we are between .struct and .ends
+    structure: Option<Structure>,
+    /// The macros declared so far
+    macros: HashMap<String, Macro>,
+    /// The macro being defined, if we are in its body
+    definition: Option<Definition>,
+    /// The number of macro expansions so far, used to give unique names to the local labels
+    expansions: usize,
+    /// The label given to the .entry directive
+    entry: Option<String>,
+    /// Warnings raised during the assembly, displayed once it is over
+    warnings: Vec<SyntaxWarning>,
+    config: Config,
 }
 
 impl Assembler {
-    fn new() -> SyntaxResult<Self> {
+    fn new(config: Config) -> SyntaxResult<Self> {
+        // The names of the interrupts are declared before the constants given with -D, which can redefine them
+        let constants = SYSCALLS
+            .iter()
+            .map(|s| (s.name.to_string(), s.number))
+            .chain(config.defines.iter().cloned())
+            .collect();
         Ok(Self {
             word_builder: cast_result(WordBuilder::new(), 0)?,
             current_line: Vec::new(),
             instructions: Vec::new(),
+            locations: Vec::new(),
+            pending_include: None,
+            once_files: HashSet::new(),
             labels: HashMap::new(),
+            constants,
+            lengths: HashSet::new(),
+            enum_value: None,
+            section: Section::default(),
+            conditions: Vec::new(),
+            structure: None,
+            macros: HashMap::new(),
+            definition: None,
+            expansions: 0,
+            entry: None,
+            warnings: Vec::new(),
+            config,
         })
     }
 
     fn push_word(&mut self, word: Word) -> SyntaxResult<()> {
-        if let WordContent::LabelDeclaration(lab) = &word.content {
+        // The labels of a structure are fields, declared once the line is over
+        if let (WordContent::LabelDeclaration(lab), None) = (&word.content, &self.structure) {
+            if is_syscall_name(lab) {
+                return cast_result(
+                    Err(SyntaxErrorKind::ReservedName(lab.to_string())),
+                    self.line(),
+                );
+            }
             if self
                 .labels
                 .insert(lab.to_string(), self.instructions.len() as u64)
                 .is_some()
+                || self.constants.contains_key(lab)
             {
                 return cast_result(
                     Err(SyntaxErrorKind::LabelDeclaredTwice(lab.to_string())),
@@ -81,10 +219,203 @@ impl Assembler {
         Ok(())
     }
 
+    fn declare_constant(
+        &mut self,
+        name: String,
+        value: i32,
+        redefinable: bool,
+    ) -> SyntaxResult<()> {
+        if is_syscall_name(&name) {
+            return cast_result(Err(SyntaxErrorKind::ReservedName(name)), self.line());
+        }
+        if self.labels.contains_key(&name) || (!redefinable && self.constants.contains_key(&name)) {
+            return cast_result(
+                Err(SyntaxErrorKind::ConstantDeclaredTwice(name)),
+                self.line(),
+            );
+        }
+        self.constants.insert(name, value);
+        Ok(())
+    }
+
+    /// Declare each name of a line inside an .enum block with the next value of the enumeration
+    fn push_enum_line(&mut self, line: Vec<Word>) -> SyntaxResult<()> {
+        for w in line {
+            match w.content {
+                WordContent::Empty => (),
+                WordContent::Label(name) => {
+                    let value = self.enum_value.unwrap();
+                    self.declare_constant(name, value, false)?;
+                    self.enum_value = Some(value.wrapping_add(1));
+                }
+                _ => {
+                    return cast_result(
+                        Err(SyntaxErrorKind::ExpectedConstantName(
+                            w.pure_content[1..w.pure_content.len() - 1].to_string(),
+                        )),
+                        self.line(),
+                    )
+                }
+            }
+        }
+        let line = cast_result(Line::new(Vec::new(), &|_| None), self.line())?;
+        self.instructions.push(line);
+        Ok(())
+    }
+
     fn push_current_line(&mut self) -> SyntaxResult<()> {
         let line = self.current_line.drain(..).collect::<Vec<_>>();
-        self.instructions
-            .push(cast_result(Line::try_from(line), self.line())?);
+        let first_word = line
+            .iter()
+            .find(|w| !matches!(w.content, WordContent::Empty) && !w.is_label_decl());
+        if self.enum_value.is_some()
+            && first_word.and_then(|w| w.get_directive()) != Some(Directive::EndEnum)
+        {
+            return self.push_enum_line(line);
+        }
+        let constants = &self.constants;
+        let mut line = cast_result(
+            Line::new(line, &|name| constants.get(name).map(|x| *x as i64)),
+            self.line(),
+        )?;
+        match line.get_directive() {
+            Some(Directive::Enum) => self.enum_value = Some(line.get_enum_start().unwrap()),
+            Some(Directive::EndEnum) if self.enum_value.take().is_none() => {
+                return cast_result(Err(SyntaxErrorKind::UnexpectedEndEnum), self.line());
+            }
+            Some(d) if d.is_condition() => {
+                let constants = &self.constants;
+                let active = line
+                    .get_condition(|name| constants.contains_key(name))
+                    .unwrap();
+                self.conditions.push(Condition {
+                    active,
+                    taken: active,
+                    has_else: false,
+                    line: self.line(),
+                })
+            }
+            Some(Directive::Else) => match self.conditions.last_mut() {
+                Some(cond) if !cond.has_else => {
+                    cond.has_else = true;
+                    cond.active = !cond.taken;
+                    cond.taken = true;
+                }
+                _ => return cast_result(Err(SyntaxErrorKind::UnexpectedElse), self.line()),
+            },
+            Some(Directive::Endif) if self.conditions.pop().is_none() => {
+                return cast_result(Err(SyntaxErrorKind::UnexpectedEndif), self.line());
+            }
+            Some(Directive::Ends) if self.structure.is_none() => {
+                return cast_result(Err(SyntaxErrorKind::UnexpectedEnds), self.line());
+            }
+            _ => (),
+        }
+        if let Some(path) = line.get_include() {
+            self.pending_include = Some(path.to_string());
+        }
+        if line.get_directive() == Some(Directive::Once) {
+            let file = self.locations.last().unwrap().file.clone();
+            self.once_files
+                .insert(canonicalize(&*file).unwrap_or(file.to_path_buf()));
+        }
+        if line.get_incbin().is_some() {
+            self.load_incbin(&mut line)?;
+        }
+        if let Some((name, value)) = line.get_constant_definition() {
+            let redefinable = line.get_directive() == Some(Directive::Set);
+            self.declare_constant(name.to_string(), value, redefinable)?;
+        }
+        if self.structure.is_some() {
+            return self.push_field(line);
+        }
+        if let Some(name) = line.get_struct_name() {
+            self.structure = Some(Structure {
+                name: name.to_string(),
+                offset: 0,
+                line: self.line(),
+            });
+        }
+        if let Some(section) = line.get_new_section() {
+            self.section = section;
+        }
+        if let Some(lab) = line.get_entry() {
+            if self.entry.replace(lab.to_string()).is_some() {
+                return cast_result(Err(SyntaxErrorKind::EntryDeclaredTwice), self.line());
+            }
+        }
+        if self.section == Section::Bss && !line.is_reservation() {
+            return cast_result(Err(SyntaxErrorKind::InitializedDataInBss), self.line());
+        }
+        line.set_section(self.section);
+        self.instructions.push(line);
+        Ok(())
+    }
+
+    /// Declare the labels of a line inside a structure as constants holding their offset, nothing is emitted for the line
+    fn push_field(&mut self, mut line: Line) -> SyntaxResult<()> {
+        if line.is_instruction() {
+            return cast_result(Err(SyntaxErrorKind::ExpectedField), self.line());
+        }
+        if line.get_directive() == Some(Directive::Struct) {
+            return cast_result(Err(SyntaxErrorKind::UnexpectedStruct), self.line());
+        }
+        let Structure { name, offset, .. } = self.structure.as_ref().unwrap();
+        let (name, offset) = (name.clone(), *offset);
+        line.set_address(offset);
+        let (labels, size) = line.get_line_info();
+        for lab in labels {
+            self.declare_constant(format!("{name}.{lab}"), offset as i32, false)?;
+        }
+        let end = offset + size as u64;
+        if line.get_directive() == Some(Directive::Ends) {
+            self.declare_constant(format!("{name}.size"), end as i32, false)?;
+            self.structure = None;
+        } else {
+            self.structure.as_mut().unwrap().offset = end;
+        }
+        self.push_empty_line();
+        Ok(())
+    }
+
+    /// Read the bytes embedded by an .incbin line, and declare the constant label.len for each label of the line or of the lines only made of labels just before
+    fn load_incbin(&mut self, line: &mut Line) -> SyntaxResult<()> {
+        let (name, offset, length) = line.get_incbin().unwrap();
+        let location = self.locations.last().unwrap();
+        let path = self.find_include(name, location, self.line())?;
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                return cast_result(
+                    Err(SyntaxErrorKind::CantReadFile(path.display().to_string())),
+                    self.line(),
+                )
+            }
+        };
+        let end = length.map_or(bytes.len(), |length| offset + length);
+        if offset > bytes.len() || end > bytes.len() {
+            return cast_result(
+                Err(SyntaxErrorKind::FileTooShort(
+                    path.display().to_string(),
+                    bytes.len(),
+                )),
+                self.line(),
+            );
+        }
+        let size = (end - offset) as i32;
+        let mut labels = line.get_labels_decl();
+        for previous in self.instructions.iter().rev() {
+            if !previous.is_empty() {
+                break;
+            }
+            labels.extend(previous.get_labels_decl());
+        }
+        for lab in labels {
+            let name = format!("{lab}.len");
+            self.declare_constant(name.clone(), size, false)?;
+            self.lengths.insert(name);
+        }
+        line.set_incbin(bytes[offset..end].to_vec());
         Ok(())
     }
 
@@ -104,82 +435,1015 @@ impl Assembler {
         Ok(())
     }
 
+    /// Handle a line inside a conditional branch that is not assembled, an empty line is pushed in its place to keep the lines numbers. Returns false if the line closes the skipped branch and so has to be assembled.
+    fn skip_line(&mut self, text: &str) -> bool {
+        let skipped = match self.conditions.iter().position(|c| !c.active) {
+            Some(i) => i,
+            None => return false,
+        };
+        let nested = skipped + 1 < self.conditions.len();
+        // Only the directive is needed, the line may not be valid
+        let directive = split_statement(text)
+            .1
+            .and_then(|w| Directive::try_from(w).ok());
+        match directive {
+            Some(d) if d.is_condition() => self.conditions.push(Condition {
+                active: false,
+                taken: true,
+                has_else: false,
+                line: self.line(),
+            }),
+            Some(Directive::Endif) if nested => {
+                self.conditions.pop();
+            }
+            Some(Directive::Else | Directive::Endif) if !nested => return false,
+            _ => (),
+        }
+        self.push_empty_line();
+        true
+    }
+
+    /// Push a line that does not contain anything, in place of a line of the sources that is not assembled
+    fn push_empty_line(&mut self) {
+        let mut line = Line::default();
+        line.set_section(self.section);
+        self.instructions.push(line);
+    }
+
+    /// Store the line in the body of the block being read if any, returns false if there is no such block
+    fn record_line(&mut self, text: &str, location: &Location) -> SyntaxResult<bool> {
+        let def = match &mut self.definition {
+            Some(def) => def,
+            None => return Ok(false),
+        };
+        let (starts, end) = def.kind.delimiters();
+        match split_statement(text).1 {
+            Some(w) if starts.contains(&w) => def.depth += 1,
+            Some(w) if w == end && def.depth == 0 => {
+                self.push_empty_line();
+                self.end_definition()?;
+                return Ok(true);
+            }
+            Some(w) if w == end => def.depth -= 1,
+            _ => (),
+        }
+        def.macro_def.push_line(text, location.clone());
+        self.push_empty_line();
+        Ok(true)
+    }
+
+    /// Store the macro whose body is over, or feed the iterations of the repetition block
+    fn end_definition(&mut self) -> SyntaxResult<()> {
+        let def = self.definition.take().unwrap();
+        let block = Rc::new(def.location);
+        let values: Vec<Option<String>> = match def.kind {
+            BlockKind::Macro => {
+                self.macros
+                    .insert(def.macro_def.name.to_string(), def.macro_def);
+                return Ok(());
+            }
+            BlockKind::Rept(count) => vec![None; count],
+            BlockKind::Irp(values) => values.into_iter().map(Some).collect(),
+        };
+        for (i, value) in values.into_iter().enumerate() {
+            let lines = def.macro_def.iterate(value, i, self.expansions, &block);
+            self.expansions += 1;
+            for (text, location) in lines {
+                self.feed_line(&text, location)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of iterations given to a .rept directive, it can only use numbers and constants
+    fn repetition_count(&self, header: &str) -> SyntaxResultKind<usize> {
+        let header = header.trim();
+        if header.is_empty() {
+            return Err(SyntaxErrorKind::ExpectedRepetitionCount);
+        }
+        let count = Expr::try_from(header)?
+            .eval(&|name| self.constants.get(name).map(|x| *x as i64))
+            .map_err(|err| match err {
+                SyntaxErrorKind::LabelIsNotDeclared(name) => {
+                    SyntaxErrorKind::ConstantNotResolved(name)
+                }
+                err => err,
+            })?;
+        usize::try_from(count).map_err(|_| SyntaxErrorKind::ExpectedRepetitionCount)
+    }
+
+    /// Start reading the body of a macro or of a repetition block, from the directive and the text following it
+    fn start_definition(
+        &mut self,
+        directive: &str,
+        header: &str,
+        location: &Location,
+    ) -> SyntaxResult<()> {
+        let (macro_def, kind) = cast_result(
+            match directive {
+                MACRO_START => Macro::new(header).map(|m| (m, BlockKind::Macro)),
+                REPT_START => self
+                    .repetition_count(header)
+                    .and_then(|n| Ok((Macro::repetition(directive, None)?, BlockKind::Rept(n)))),
+                _ => {
+                    let (param, values) = split_irp_header(header);
+                    Macro::repetition(directive, Some(param)).map(|m| (m, BlockKind::Irp(values)))
+                }
+            },
+            self.line(),
+        )?;
+        if matches!(kind, BlockKind::Macro) && self.macros.contains_key(&*macro_def.name) {
+            return cast_result(
+                Err(SyntaxErrorKind::MacroDeclaredTwice(
+                    macro_def.name.to_string(),
+                )),
+                self.line(),
+            );
+        }
+        self.definition = Some(Definition {
+            macro_def,
+            kind,
+            depth: 0,
+            line: self.line(),
+            location: location.clone(),
+        });
+        self.push_empty_line();
+        Ok(())
+    }
+
+    /// Returns the lines of the expansion if the line invokes a macro
+    fn expand_macro(
+        &mut self,
+        name: &str,
+        args: &str,
+        location: &Location,
+    ) -> SyntaxResult<Option<Vec<(String, Location)>>> {
+        let macro_def = match self.macros.get(name) {
+            Some(macro_def) => macro_def,
+            None => return Ok(None),
+        };
+        if location.expansion_depth() >= MAX_EXPANSION_DEPTH {
+            return cast_result(
+                Err(SyntaxErrorKind::MacroTooDeep(name.to_string())),
+                self.line(),
+            );
+        }
+        let lines = cast_result(
+            macro_def.expand(args, self.expansions, &Rc::new(location.clone())),
+            self.line(),
+        )?;
+        self.expansions += 1;
+        Ok(Some(lines))
+    }
+
+    /// Returns the lines of real instructions if the line is a pseudo-instruction, they are located on the line of the pseudo-instruction
+    fn expand_pseudo(
+        &self,
+        name: &str,
+        operands: &str,
+        location: &Location,
+    ) -> SyntaxResult<Option<Vec<(String, Location)>>> {
+        let lines = match pseudo::expand(name, operands) {
+            Some(lines) => cast_result(lines, self.line())?,
+            None => return Ok(None),
+        };
+        Ok(Some(
+            lines
+                .into_iter()
+                .map(|text| (text, location.clone()))
+                .collect(),
+        ))
+    }
+
+    /// Feed the assembler with a line of the sources, the line is pushed even if it does not end with a newline. The files included are fed as soon as their .include line is over, and the lines of a macro or of a pseudo-instruction are fed in place of it.
+    fn feed_line(&mut self, text: &str, location: Location) -> SyntaxResult<()> {
+        self.locations.push(location.clone());
+        if self.record_line(text, &location)? || self.skip_line(text) {
+            return Ok(());
+        }
+        let (labels, first_word, rest) = split_statement(text);
+        let expansion = match first_word {
+            // The labels of the line are declared on their own line before the block, so they point to its first expansion
+            Some(directive @ (MACRO_START | REPT_START | IRP_START)) => {
+                if !labels.trim().is_empty() {
+                    self.feed_words(labels, &location)?;
+                    self.locations.push(location.clone());
+                }
+                return self.start_definition(directive, rest, &location);
+            }
+            Some(MACRO_END) => {
+                return cast_result(Err(SyntaxErrorKind::UnexpectedEndm), self.line())
+            }
+            Some(REPT_END) => {
+                return cast_result(Err(SyntaxErrorKind::UnexpectedEndr), self.line())
+            }
+            Some(name) => match self.expand_macro(name, rest, &location)? {
+                Some(lines) => Some(lines),
+                None => self.expand_pseudo(name, rest, &location)?,
+            },
+            None => None,
+        };
+        // Only the labels of a macro invocation or of a pseudo-instruction are kept on its line, the line is dropped if it has none
+        let text = match expansion {
+            Some(_) if labels.trim().is_empty() => None,
+            Some(_) => Some(labels),
+            None => Some(text),
+        };
+        match text {
+            Some(text) => self.feed_words(text, &location)?,
+            None => {
+                self.locations.pop();
+            }
+        }
+        for (text, location) in expansion.into_iter().flatten() {
+            self.feed_line(&text, location)?;
+        }
+        Ok(())
+    }
+
+    /// Build the words of a line, then feed the file included by the line if any
+    fn feed_words(&mut self, text: &str, location: &Location) -> SyntaxResult<()> {
+        let mut chars = text.chars().chain((!text.ends_with('\n')).then_some('\n'));
+        while let Some(c) = chars.next() {
+            self.consume(c, &mut chars)?;
+        }
+        cast_result(self.word_builder.check_end_of_line(), self.line())?;
+        if let Some(name) = self.pending_include.take() {
+            let path = self.find_include(&name, location, self.line() - 1)?;
+            self.feed_file(&path, Some(Rc::new(location.clone())))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the path of a file to include, it is searched next to the current file then in the include paths
+    fn find_include(&self, name: &str, location: &Location, line: usize) -> SyntaxResult<PathBuf> {
+        let current_dir = location
+            .file
+            .parent()
+            .unwrap_or(Path::new(""))
+            .to_path_buf();
+        let path = std::iter::once(&current_dir)
+            .chain(&self.config.include_paths)
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file());
+        match path {
+            Some(path) => Ok(path),
+            None => cast_result(
+                Err(SyntaxErrorKind::IncludeNotFound(name.to_string())),
+                line,
+            ),
+        }
+    }
+
+    /// Feed the assembler with all the lines of a file
+    fn feed_file(&mut self, path: &Path, included_from: Option<Rc<Location>>) -> SyntaxResult<()> {
+        let line = self.line() - 1;
+        let canonical_path = canonicalize(path).unwrap_or(path.to_path_buf());
+        if self.once_files.contains(&canonical_path) {
+            return Ok(());
+        }
+        if included_from.as_ref().is_some_and(|l| {
+            l.chain()
+                .any(|l| canonicalize(&*l.file).unwrap_or(l.file.to_path_buf()) == canonical_path)
+        }) {
+            return cast_result(
+                Err(SyntaxErrorKind::IncludeCycle(path.display().to_string())),
+                line,
+            );
+        }
+        let text = match read_to_string(path) {
+            Ok(text) => text,
+            Err(_) => {
+                return cast_result(
+                    Err(SyntaxErrorKind::CantReadFile(path.display().to_string())),
+                    line,
+                )
+            }
+        };
+        if text.is_empty() && included_from.is_none() {
+            return cast_result(Err(SyntaxErrorKind::EmptyText), 0);
+        }
+
+        self.feed_text(&text, Rc::new(path.to_path_buf()), included_from)
+    }
+
+    /// Feed the assembler with the lines of a text read from the given file
+    fn feed_text(
+        &mut self,
+        text: &str,
+        file: Rc<PathBuf>,
+        included_from: Option<Rc<Location>>,
+    ) -> SyntaxResult<()> {
+        for (i, text) in text.split_inclusive('\n').enumerate() {
+            let location = Location {
+                file: file.clone(),
+                line: i + 1,
+                parent: included_from.clone(),
+                origin: Origin::Include,
+            };
+            self.feed_line(text, location)?;
+        }
+        Ok(())
+    }
+
+    /// Replace the lengths of the .incbin lines used before their declaration
+    fn fold_lengths(&mut self) -> SyntaxResult<()> {
+        let (constants, lengths) = (&self.constants, &self.lengths);
+        let resolve = |name: &str| lengths.contains(name).then(|| constants[name] as i64);
+        for (i, line) in self.instructions.iter_mut().enumerate() {
+            cast_result(line.fold_constants(&resolve), i + 1)?;
+        }
+        Ok(())
+    }
+
     fn check_labels_validity(&self) -> SyntaxResult<()> {
         for (i, line) in self.instructions.iter().enumerate() {
-            if let Some(lab) = line.get().iter().find(|word| {
-                if let WordContent::Label(lab) = &word.content {
-                    !self.labels.contains_key(lab)
-                } else {
-                    false
-                }
-            }) {
-                match &lab.content {
-                    WordContent::Label(lab) => {
+            if let Some(lab) = line
+                .get_used_labels()
+                .into_iter()
+                .find(|lab| !self.labels.contains_key(*lab))
+            {
+                return cast_result(
+                    Err(SyntaxErrorKind::LabelIsNotDeclared(lab.to_string())),
+                    i + 1,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Verify that the label given with the option --entry exists, the one of the .entry directive is checked as any other used label
+    fn check_if_entry_exists(&self) -> SyntaxResult<()> {
+        match &self.config.entry {
+            Some(lab) if !self.labels.contains_key(lab) => {
+                cast_result(Err(SyntaxErrorKind::EntryNotFound(lab.clone())), 0)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the address where the execution starts: the label given with --entry, then the one given to .entry, then main, and at last the start of the code section
+    fn entry_address(&self) -> u64 {
+        let entry = self.config.entry.as_ref().or(self.entry.as_ref());
+        match entry
+            .map(|lab| self.labels[lab])
+            .or(self.labels.get("main").copied())
+        {
+            Some(addr) => addr,
+            None => self
+                .instructions
+                .iter()
+                .find(|l| l.section() == Section::Text)
+                .map_or(align_up(self.config.base, 4), |l| l.address()),
+        }
+    }
+
+    /// Compute the address of each line and label. The sections are laid out one after the other from the base address, each one starting on an address aligned on 4 bytes. When the auto alignment is enabled, the labels alone on their line are attached to the next line, so they follow it if it is moved.
+    fn correct_labels_addresses(&mut self) -> SyntaxResult<()> {
+        let base = self.config.base;
+        let mut addr = base;
+        for section in Section::LAYOUT {
+            addr = align_up(addr, 4);
+            let mut pending_labels = Vec::new();
+            for (i, line) in self
+                .instructions
+                .iter_mut()
+                .enumerate()
+                .filter(|(_, l)| l.section() == section)
+            {
+                if let Some(target) = line.get_org() {
+                    if target < base {
                         return cast_result(
-                            Err(SyntaxErrorKind::LabelIsNotDeclared(lab.to_string())),
+                            Err(SyntaxErrorKind::OrgBelowBase(target, base)),
                             i + 1,
-                        )
+                        );
                     }
-                    _ => unreachable!(),
+                    if target < addr {
+                        return cast_result(Err(SyntaxErrorKind::OrgOverlap(target, addr)), i + 1);
+                    }
+                    addr = target;
+                }
+                if self.config.auto_align && line.is_instruction() {
+                    addr = align_up(addr, 4);
                 }
+                line.set_address(addr);
+                let (labels, size) = line.get_line_info();
+                pending_labels.extend(labels);
+                if !self.config.auto_align || !line.is_empty() {
+                    for l in pending_labels.drain(..) {
+                        self.labels.insert(l, addr);
+                    }
+                }
+                addr += size as u64;
+            }
+            for l in pending_labels {
+                self.labels.insert(l, addr);
             }
         }
         Ok(())
     }
 
-    fn check_if_main_exists(&self) -> SyntaxResult<()> {
-        if !self.labels.contains_key("main") {
-            cast_result(Err(SyntaxErrorKind::NoMain), 0)
-        } else {
-            Ok(())
+    fn check_jump_targets_alignment(&mut self) {
+        for (i, line) in self.instructions.iter().enumerate() {
+            if let Some(lab) = line.get_jump_target() {
+                let addr = self.labels[lab];
+                if !addr.is_multiple_of(4) {
+                    self.warnings.push(SyntaxWarning::new(
+                        SyntaxWarningKind::MisalignedJumpTarget(lab.to_string(), addr),
+                        i + 1,
+                    ))
+                }
+            }
         }
     }
 
-    fn correct_labels_addresses(&mut self) {
-        let mut addr = 0;
-        for line in &self.instructions {
-            let (labels, size) = line.get_line_info();
-            for l in labels {
-                self.labels.insert(l, addr);
+    /// Warn about the INT instructions given a number that is not an interrupt of the ZVM
+    fn check_interrupts(&mut self) {
+        for (i, line) in self.instructions.iter().enumerate() {
+            let number = match line.get_interrupt().map(|w| w.get_value(&self.labels)) {
+                Some(Ok(number)) => number,
+                _ => continue, // An invalid value is reported when the line is encoded
+            };
+            if find_syscall(number).is_none() {
+                self.warnings.push(SyntaxWarning::new(
+                    SyntaxWarningKind::UnknownInterrupt(number),
+                    i + 1,
+                ))
             }
-            addr += size as u64;
         }
     }
 
+    /// Evaluate the .assert, .error, .warning and .print directives, now that the labels are laid out
+    fn check_diagnostics(&mut self) -> SyntaxResult<()> {
+        for (i, line) in self.instructions.iter().enumerate() {
+            let (directive, operands) = match line.get_diagnostic() {
+                Some(diagnostic) => diagnostic,
+                None => continue,
+            };
+            match directive {
+                Directive::Assert => {
+                    if cast_result(operands[0].get_value(&self.labels), i + 1)? == 0 {
+                        let msg = operands.get(1).map(|w| w.get_str().unwrap().to_string());
+                        return cast_result(Err(SyntaxErrorKind::AssertionFailed(msg)), i + 1);
+                    }
+                }
+                Directive::Error => {
+                    let msg = operands[0].get_str().unwrap().to_string();
+                    return cast_result(Err(SyntaxErrorKind::UserError(msg)), i + 1);
+                }
+                Directive::Warning => self.warnings.push(SyntaxWarning::new(
+                    SyntaxWarningKind::User(operands[0].get_str().unwrap().to_string()),
+                    i + 1,
+                )),
+                _ => {
+                    let mut text = Vec::new();
+                    for w in operands {
+                        text.push(match w.get_str() {
+                            Some(s) => s.to_string(),
+                            None => cast_result(w.get_value(&self.labels), i + 1)?.to_string(),
+                        });
+                    }
+                    println!("{}", text.join(" "));
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn conclude(&mut self) -> SyntaxResult<()> {
-        self.check_if_main_exists()?;
-        self.current_line
-            .push(cast_result(self.word_builder.end_of_file(), self.line())?);
-        self.push_current_line()?;
+        self.check_if_entry_exists()?;
+        if self.enum_value.is_some() {
+            return cast_result(Err(SyntaxErrorKind::EnumNeverEnded), self.line() - 1);
+        }
+        if let Some(structure) = &self.structure {
+            return cast_result(Err(SyntaxErrorKind::StructNeverEnded), structure.line);
+        }
+        if let Some(def) = &self.definition {
+            let err = match def.kind {
+                BlockKind::Macro => SyntaxErrorKind::MacroNeverEnded,
+                _ => SyntaxErrorKind::RepetitionNeverEnded,
+            };
+            return cast_result(Err(err), def.line);
+        }
+        if let Some(cond) = self.conditions.last() {
+            return cast_result(Err(SyntaxErrorKind::IfNeverEnded), cond.line);
+        }
+        self.fold_lengths()?;
         self.check_labels_validity()?;
-        self.correct_labels_addresses();
-        Ok(())
+        self.correct_labels_addresses()?;
+        self.check_jump_targets_alignment();
+        self.check_interrupts();
+        self.check_diagnostics()
     }
 
-    fn generate_binary(&self, mut output_file: File) -> Result<(), IoError> {
-        let origin = *self.labels.get("main").unwrap() as u32;
-        output_file.write(&origin.to_be_bytes())?;
-        for line in &self.instructions {
-            output_file.write(&line.get_binary_instruction(&self.labels))?;
+    fn generate_binary(&self) -> SyntaxResult<Vec<u8>> {
+        let origin = self.entry_address() as u32;
+        let mut binary = FORMAT_VERSION.to_be_bytes().to_vec();
+        binary.extend_from_slice(&origin.to_be_bytes());
+        let mut addr = self.config.base;
+        for section in Section::LAYOUT.into_iter().filter(|s| *s != Section::Bss) {
+            for (i, line) in self
+                .instructions
+                .iter()
+                .enumerate()
+                .filter(|(_, l)| l.section() == section)
+            {
+                binary.resize(binary.len() + (line.address() - addr) as usize, 0);
+                let mut bytes = cast_result(line.get_binary_instruction(&self.labels), i + 1)?;
+                addr = line.address() + bytes.len() as u64;
+                binary.append(&mut bytes);
+            }
         }
-        Ok(())
+        Ok(binary)
     }
 }
 
-pub fn assemble(output_file: File, text: &str) -> SyntaxResult<()> {
-    if text.is_empty() {
-        return cast_result(Err(SyntaxErrorKind::EmptyText), 0);
+/// Assemble the file and all the files it includes, the diagnostics are located in the sources
+fn run(assembler: &mut Assembler, path: &Path) -> SyntaxResult<Vec<u8>> {
+    assembler.feed_file(path, None)?;
+    assembler.conclude()?;
+    assembler.generate_binary()
+}
+
+/// Assemble the file and returns the binary, nothing is written so a failed assembly leaves the previous output as is
+pub fn assemble(path: &Path, config: Config) -> SyntaxResult<Vec<u8>> {
+    let mut assembler = Assembler::new(config)?;
+    let binary = run(&mut assembler, path).map_err(|err| err.locate(&assembler.locations))?;
+    for warning in assembler.warnings.drain(..) {
+        println!("{:?}", warning.locate(&assembler.locations));
     }
-    let mut chars = text.chars();
-    let mut assembler = Assembler::new()?;
-    while let Some(c) = chars.next() {
-        assembler.consume(c, &mut chars)?;
+    if assembler.config.listing {
+        display_lines(&assembler.instructions);
     }
+    Ok(binary)
+}
 
-    assembler.conclude()?;
-    display_lines(&assembler.instructions);
-    assembler
-        .generate_binary(output_file)
-        .expect("Failed to generte binary");
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Assemble a source given as a main file, returns the binary without its header
+    fn assemble_source(source: &str) -> Vec<u8> {
+        try_assemble(source).unwrap()
+    }
+
+    /// Returns the instructions assembled from a source given as a main file
+    pub(super) fn words(source: &str) -> Vec<u32> {
+        assemble_source(source)
+            .chunks(4)
+            .map(|w| u32::from_be_bytes(w.try_into().unwrap()))
+            .collect()
+    }
+
+    /// Assemble a source given as a main file, the errors are located in it
+    pub(super) fn try_assemble(source: &str) -> SyntaxResult<Vec<u8>> {
+        Ok(assemble_with(Config::default(), source)?[8..].to_vec())
+    }
+
+    /// Assemble a source with the given options, returns the whole binary with its header
+    fn assemble_with(config: Config, source: &str) -> SyntaxResult<Vec<u8>> {
+        assemble_in(config, Path::new(""), source)
+    }
+
+    /// Assemble a source as if it was a file of the given directory, so it can include the files of the directory
+    fn assemble_in(config: Config, dir: &Path, source: &str) -> SyntaxResult<Vec<u8>> {
+        let mut assembler = Assembler::new(config)?;
+        assembler
+            .feed_text(source, Rc::new(dir.join("test.nzm")), None)
+            .and_then(|_| assembler.conclude())
+            .and_then(|_| assembler.generate_binary())
+            .map_err(|err| err.locate(&assembler.locations))
+    }
+
+    #[test]
+    fn first_line_can_be_blank_or_indented() {
+        assert_eq!(
+            assemble_source("\nmain:\n    halt\n"),
+            assemble_source("halt\n")
+        );
+        assert_eq!(assemble_source("    halt\n"), assemble_source("halt\n"));
+    }
+
+    #[test]
+    fn macro_invoked_on_the_first_line() {
+        let two = ".macro two\n    halt\n    halt\n.endm\n";
+        assert_eq!(
+            assemble_source(&format!("{two}two\n")),
+            assemble_source("halt\nhalt\n")
+        );
+        assert_eq!(
+            assemble_source(&format!("{two}\nmain:\n    two\n")),
+            assemble_source("halt\nhalt\n")
+        );
+    }
+
+    #[test]
+    fn macro_invoked_at_the_end_of_the_file() {
+        assert_eq!(
+            assemble_source("main:\n    trace\n.macro two\n    halt\n    halt\n.endm\n    two"),
+            assemble_source("trace\nhalt\nhalt\n")
+        );
+    }
+
+    #[test]
+    fn macro_invocation_keeps_its_labels() {
+        let source = ".macro stop\n    halt\n.endm\n    trace\nend: stop\n    jmp end\n";
+        assert_eq!(
+            assemble_source(source),
+            assemble_source("trace\nend: halt\njmp end\n")
+        );
+    }
+
+    #[test]
+    fn data_directives() {
+        assert_eq!(
+            assemble_source(".byte 1, -1, 'A'\n.half 0x1234\n.word -2\n"),
+            [1, 0xFF, b'A', 0x12, 0x34, 0xFF, 0xFF, 0xFF, 0xFE]
+        );
+        assert_eq!(assemble_source(".zero 3\n"), [0, 0, 0]);
+        assert_eq!(
+            assemble_source(".ascii \"ab\", \"c\"\n.asciz \"d\"\n\"e\"\n"),
+            b"abcd\0e\0"
+        );
+        // A label in a .word is replaced by its address
+        assert_eq!(
+            assemble_source("halt\nhere: .word here\n"),
+            assemble_source("halt\n.word 4\n")
+        );
+    }
+
+    #[test]
+    fn data_out_of_range() {
+        for source in [".byte 256\n", ".half -32769\n", ".zero -1\n", ".ascii 3\n"] {
+            assert!(try_assemble(source).is_err(), "{source}");
+        }
+    }
+
+    #[test]
+    fn alignment_pads_the_address() {
+        assert_eq!(assemble_source(".byte 1\n.balign 4\n"), [1, 0, 0, 0]);
+        assert_eq!(
+            assemble_source(".byte 1\n.align 2, 0xFF\n.byte 2\n"),
+            [1, 0xFF, 0xFF, 0xFF, 2]
+        );
+        assert_eq!(
+            assemble_source("halt\n.balign 8, nop\n"),
+            assemble_source("halt\nmov r0, r0\n")
+        );
+        assert!(try_assemble(".balign 3\n").is_err());
+        assert!(try_assemble(".align 1, 256\n").is_err());
+    }
+
+    #[test]
+    fn auto_align_moves_the_instructions_after_data() {
+        let config = Config {
+            auto_align: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            assemble_with(config, ".byte 1\nhalt\n").unwrap()[8..],
+            assemble_source(".byte 1\n.balign 4\nhalt\n")
+        );
+    }
+
+    #[test]
+    fn sections_are_laid_out_in_order() {
+        assert_eq!(
+            assemble_source(
+                ".section .data\nvalue: .word 7\n.section .rodata\n.byte 1\n.section .text\nrd r0, [value]\n"
+            ),
+            assemble_source("rd r0, [value]\n.byte 1\n.balign 4\nvalue: .word 7\n")
+        );
+    }
+
+    #[test]
+    fn bss_only_reserves_space() {
+        assert_eq!(
+            assemble_source(".section .bss\nbuf: .zero 16\n.section .text\nmov r0, buf\nhalt\n"),
+            assemble_source("mov r0, 8\nhalt\n")
+        );
+        assert!(try_assemble(".section .bss\n.byte 1\n").is_err());
+        // The directives emitting no byte are accepted in the bss section
+        assert_eq!(
+            assemble_source(".section .bss\n.equ SIZE, 8\n.enum\n    A, B\n.endenum\n.set B, 4\nbuf: .zero SIZE + B\n.section .text\nmov r0, buf\nhalt\n"),
+            assemble_source("mov r0, 8\nhalt\n")
+        );
+        assert!(try_assemble(".section .stack\n").is_err());
+    }
+
+    #[test]
+    fn constants_are_replaced_by_their_value() {
+        assert_eq!(
+            assemble_source(".equ SIZE, 4\n.zero SIZE * 2\nmov r0, SIZE - 1\n"),
+            assemble_source(".zero 8\nmov r0, 3\n")
+        );
+        assert_eq!(
+            assemble_source(".set N, 1\n.byte N\n.set N, N + 1\n.byte N\n"),
+            [1, 2]
+        );
+        assert_eq!(
+            assemble_source(".enum 5\n    A\n    B, C\n.endenum\n.byte A, B, C\n"),
+            [5, 6, 7]
+        );
+    }
+
+    #[test]
+    fn constants_are_declared_once_and_before_use() {
+        for source in [
+            ".equ A, 1\n.equ A, 2\n",
+            "A: halt\n.equ A, 1\n",
+            ".equ A, 1\nA: halt\n",
+            "mov r0, A\n.equ A, 1\n",
+        ] {
+            assert!(try_assemble(source).is_err(), "{source}");
+        }
+    }
+
+    /// Create a temporary directory holding the given files, for the tests reading files
+    fn files_dir(test: &str, files: &[(&str, &[u8])]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nuzima-{test}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (name, content) in files {
+            let path = dir.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn included_files_are_assembled_in_place() {
+        let dir = files_dir(
+            "include",
+            &[
+                ("io.nzm", b".once\n.include \"lib/exit.nzm\"\ntrace\n"),
+                ("lib/exit.nzm", b"halt\n"),
+                ("other/dup.nzm", b"dup\n"),
+            ],
+        );
+        let config = || Config {
+            include_paths: vec![dir.join("other")],
+            ..Config::default()
+        };
+        let source = ".include \"io.nzm\"\n.include \"io.nzm\"\n.include \"dup.nzm\"\nswap\n";
+        assert_eq!(
+            assemble_in(config(), &dir, source).unwrap()[8..],
+            assemble_source("halt\ntrace\ndup\nswap\n")
+        );
+        assert!(assemble_in(config(), &dir, ".include \"missing.nzm\"\n").is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reservations_can_be_included_in_bss() {
+        let dir = files_dir(
+            "bss",
+            &[("buffers.nzm", b".once\n.equ SIZE, 16\nbuf: .zero SIZE\n")],
+        );
+        let source = ".section .bss\n.include \"buffers.nzm\"\n.include \"buffers.nzm\"\nend: .zero 4\n.section .text\nmov r0, end\nhalt\n";
+        assert_eq!(
+            assemble_in(Config::default(), &dir, source).unwrap()[8..],
+            assemble_source("mov r0, 24\nhalt\n")
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn recursive_include_is_an_error() {
+        let dir = files_dir(
+            "recursive",
+            &[
+                ("a.nzm", b".include \"b.nzm\"\n"),
+                ("b.nzm", b".include \"a.nzm\"\n"),
+            ],
+        );
+        let err = assemble_in(Config::default(), &dir, ".include \"a.nzm\"\n").unwrap_err();
+        assert!(format!("{err:?}").contains("a.nzm"), "{err:?}");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn incbin_emits_the_bytes_and_declares_their_length() {
+        let dir = files_dir("incbin", &[("font.bin", &[1, 2, 3, 4, 5, 6])]);
+        let source = "mov r1, font.len\nfont: .incbin \"font.bin\", 1, 3\n.byte font.len\n";
+        assert_eq!(
+            assemble_in(Config::default(), &dir, source).unwrap()[8..],
+            assemble_source("mov r1, 3\n.byte 2, 3, 4, 3\n")
+        );
+        // The length is a constant, not an address
+        let source = "blob:\n.incbin \"font.bin\"\n.word blob.len\n";
+        assert_eq!(
+            assemble_in(Config::default(), &dir, source).unwrap()[8..],
+            assemble_source(".byte 1, 2, 3, 4, 5, 6\n.word 6\n")
+        );
+        assert!(assemble_in(Config::default(), &dir, ".incbin \"font.bin\", 7\n").is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn org_moves_forward_from_the_base() {
+        assert_eq!(
+            assemble_source("halt\n.org 8\nend: jmp end\n"),
+            assemble_source("halt\n.zero 4\njmp 8\n")
+        );
+        let config = Config {
+            base: 0x1000,
+            ..Config::default()
+        };
+        let binary = assemble_with(config, "mov r0, here\n.org 0x1008\nhere: halt\n").unwrap();
+        assert_eq!(binary[4..8], [0, 0, 0x10, 0]);
+        assert_eq!(
+            binary[8..],
+            assemble_source("mov r0, 0x1008\n.zero 4\nhalt\n")
+        );
+        assert!(try_assemble("halt\nhalt\n.org 4\n").is_err());
+        let config = Config {
+            base: 0x1000,
+            ..Config::default()
+        };
+        assert!(assemble_with(config, ".org 0x800\n").is_err());
+    }
+
+    /// Returns the entry address written in the header
+    fn entry(config: Config, source: &str) -> SyntaxResult<u32> {
+        let binary = assemble_with(config, source)?;
+        Ok(u32::from_be_bytes(binary[4..8].try_into().unwrap()))
+    }
+
+    #[test]
+    fn entry_point_precedence() {
+        let source = "halt\nmain: halt\nstart: halt\nother: halt\n";
+        assert_eq!(entry(Config::default(), "halt\n").unwrap(), 0);
+        assert_eq!(entry(Config::default(), source).unwrap(), 4);
+        let with_directive = format!("{source}.entry start\n");
+        assert_eq!(entry(Config::default(), &with_directive).unwrap(), 8);
+        let config = Config {
+            entry: Some(String::from("other")),
+            ..Config::default()
+        };
+        assert_eq!(entry(config, &with_directive).unwrap(), 12);
+        assert!(entry(Config::default(), ".entry nowhere\nhalt\n").is_err());
+    }
+
+    #[test]
+    fn conditional_blocks() {
+        let source = ".ifdef DEBUG\n    trace\n.else\n    .if LEVEL > 1\n        dup\n    .else\n        swap\n    .endif\n.endif\nhalt\n";
+        let with = |defines: &[(&str, i32)]| {
+            let config = Config {
+                defines: defines
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), *value))
+                    .collect(),
+                ..Config::default()
+            };
+            assemble_with(config, source).unwrap()[8..].to_vec()
+        };
+        assert_eq!(with(&[("DEBUG", 1)]), assemble_source("trace\nhalt\n"));
+        assert_eq!(with(&[("LEVEL", 2)]), assemble_source("dup\nhalt\n"));
+        assert_eq!(with(&[("LEVEL", 1)]), assemble_source("swap\nhalt\n"));
+    }
+
+    #[test]
+    fn skipped_lines_are_not_assembled() {
+        assert_eq!(
+            assemble_source(".if 0\n    not an instruction\n.endif\nhalt\n"),
+            assemble_source("halt\n")
+        );
+        for source in [
+            ".if 1\nhalt\n",
+            ".endif\n",
+            ".else\n",
+            ".if UNKNOWN\n.endif\n",
+        ] {
+            assert!(try_assemble(source).is_err(), "{source}");
+        }
+    }
+
+    #[test]
+    fn repetition_blocks_are_expanded() {
+        assert_eq!(
+            assemble_source(".rept 2\n    .rept 2\n        .byte \\#\n    .endr\n.endr\n"),
+            [0, 1, 0, 1]
+        );
+        assert_eq!(
+            assemble_source(
+                ".irp reg, r1, r2\n\\reg\\()_loop: push \\reg\n    jmp \\reg\\()_loop\n.endr\n"
+            ),
+            assemble_source("push r1\njmp 0\npush r2\njmp 8\n")
+        );
+    }
+
+    #[test]
+    fn structures_declare_their_offsets() {
+        let source = ".struct Point\nx: .word 0\ny: .half 0\nz: .byte 0\n.ends\n.byte Point.x, Point.y, Point.z, Point.size\n";
+        assert_eq!(assemble_source(source), [0, 4, 6, 7]);
+        assert!(try_assemble(".struct Point\nhalt\n.ends\n").is_err());
+        assert!(try_assemble(".struct Point\n").is_err());
+    }
+
+    #[test]
+    fn assertions_use_the_laid_out_labels() {
+        assert!(try_assemble("start: .assert end - start == 8\nhalt\nhalt\nend:\n").is_ok());
+        let err = try_assemble("start: halt\n.assert end - start > 8, \"too short\"\nend:\n")
+            .unwrap_err();
+        let message = format!("{err:?}");
+        assert!(
+            message.contains("line 2") && message.contains("too short"),
+            "{message}"
+        );
+        assert!(try_assemble(".error \"unsupported\"\n").is_err());
+        assert!(try_assemble(".warning \"careful\"\n.print \"size:\", 4\nhalt\n").is_ok());
+    }
+
+    #[test]
+    fn labels_of_a_block_point_to_its_expansion() {
+        assert_eq!(
+            assemble_source("halt\ntable: .rept 2\n    .word \\#\n.endr\nmov r0, table\n"),
+            assemble_source("halt\n.word 0, 1\nmov r0, 4\n")
+        );
+        assert_eq!(
+            assemble_source("regs: .irp reg, r1, r2\n    push \\reg\n.endr\njmp regs\n"),
+            assemble_source("push r1\npush r2\njmp 0\n")
+        );
+        assert_eq!(
+            assemble_source("here: .macro stop\n    halt\n.endm\nstop\njmp here\n"),
+            assemble_source("halt\njmp 0\n")
+        );
+    }
+
+    #[test]
+    fn pseudo_instruction_on_the_first_line() {
+        assert_eq!(
+            assemble_source("nop\nhalt\n"),
+            assemble_source("mov r0, r0\nhalt\n")
+        );
+        assert_eq!(assemble_source("inc r0"), assemble_source("add r0, 1"));
+        assert_eq!(
+            assemble_source("push r0, r1\n"),
+            assemble_source("push r0\npush r1\n")
+        );
+    }
+
+    #[test]
+    fn li_loads_a_whole_word() {
+        assert_eq!(
+            assemble_source("li r0, 0x12345678\n"),
+            assemble_source("mov r0, 0x1234\nshl r0, 16\nor r0, 0x5678\n")
+        );
+        assert_eq!(
+            assemble_source("li r1, -1\n"),
+            assemble_source("mov r1, 0xFFFF\nshl r1, 16\nor r1, 0xFFFF\n")
+        );
+        // The label is placed after the 3 instructions of li
+        assert_eq!(
+            assemble_source("li r2, end\nend: halt\n"),
+            assemble_source("mov r2, 0\nshl r2, 16\nor r2, 12\nhalt\n")
+        );
+    }
+
+    #[test]
+    fn interrupts_are_given_by_name() {
+        assert_eq!(
+            assemble_source("int SYS_WRITE\nint SYS_READ\n"),
+            assemble_source("int 1\nint 0\n")
+        );
+    }
+
+    #[test]
+    fn interrupt_names_are_reserved() {
+        for source in [
+            "SYS_WRITE: halt\n",
+            ".equ SYS_WRITE, 3\n",
+            ".set SYS_EXIT, 3\n",
+            ".enum\n    SYS_FREE\n.endenum\n",
+        ] {
+            let err = try_assemble(source).unwrap_err();
+            assert!(
+                format!("{err:?}").contains("interrupt of the ZVM"),
+                "{source}: {err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn define_can_redefine_an_interrupt() {
+        let mut config = Config::default();
+        config.defines.push((String::from("SYS_WRITE"), 7));
+        let binary = assemble_with(config, "int SYS_WRITE\n").unwrap();
+        assert_eq!(binary[8..], assemble_source("int 7\n"));
+    }
+
+    #[test]
+    fn error_in_a_macro_is_located_in_its_body() {
+        let err = try_assemble(".macro bad\n    add r0\n.endm\nmain:\n    bad\n").unwrap_err();
+        let message = format!("{err:?}");
+        assert!(
+            message.starts_with("Error in test.nzm line 2,"),
+            "{message}"
+        );
+        assert!(
+            message.contains("expanded from test.nzm line 5"),
+            "{message}"
+        );
+    }
 }
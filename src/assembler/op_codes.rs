@@ -2,9 +2,11 @@ use crate::as_number;
 
 use super::{
     errors::{SyntaxErrorKind, SyntaxResultKind},
+    isa::{Form, Instruction, ISA},
     word::Word,
 };
 
+// JH, JL, JLE and JHE compare signed values, JA, JB, JBE and JAE compare unsigned ones
 as_number!(
     u8,
     enum JCODE {
@@ -14,9 +16,69 @@ as_number!(
         JL,
         JLE,
         JHE,
+        JNE,
+        JB,
+        JA,
+        JBE,
+        JAE,
     },
     derive(Clone, Copy, Debug, Eq, PartialEq)
 );
+
+// B and H are zero extended when read, BS and HS are sign extended, only B and H can be written
+as_number!(
+    u8,
+    enum WIDTH {
+        B,
+        BS,
+        H,
+        HS,
+    },
+    derive(Clone, Copy, Debug, Eq, PartialEq)
+);
+
+/// The jump condition is encoded on 4 bits from this bit, followed by the flag of the operand
+pub const JCODE_SHIFT: u32 = 23;
+
+/// The destination register of a two operands instruction is encoded on 4 bits from this bit, followed by the flag of the source
+pub const DST_SHIFT: u32 = 23;
+
+/// The signed offset of a memory operand is encoded on the bits between this bit and the flag of the second operand, above the register
+pub const OFFSET_SHIFT: u32 = 4;
+
+/// The width of a byte or half word access is encoded on 2 bits from this bit, followed by the flag of the second operand
+pub const WIDTH_SHIFT: u32 = 21;
+
+/// Op code of the three operands instructions, the op code of the two operands form is stored after the destination and the first source
+pub const THREE_OPERANDS: u8 = 29;
+
+/// The first source of a three operands instruction is encoded on 4 bits from this bit
+pub const SRC_SHIFT: u32 = 19;
+
+/// The op code of the two operands form of a three operands instruction is encoded on 4 bits from this bit, followed by the flag of the second source
+pub const ALU_SHIFT: u32 = 15;
+
+/// Op code of the conditional moves and sets, the condition is encoded on 4 bits from the JCODE_SHIFT bit
+pub const CONDITIONAL: u8 = 30;
+
+/// The destination register of a conditional instruction is encoded on 4 bits from this bit, after the condition
+pub const COND_DST_SHIFT: u32 = 19;
+
+/// Set for a conditional set, clear for a conditional move followed by the flag of its source
+pub const SET_BIT: u32 = 18;
+
+/// Op code of the extended instructions, their own code is encoded on 6 bits from this bit and their operands are laid out under it as the ones of the other instructions are under the op code
+pub const EXTENDED: u8 = 31;
+
+/// The code of an extended instruction is encoded from this bit
+pub const EXT_SHIFT: u32 = 21;
+
+/// Stored in place of the base register of a WRITE when the address is absolute, it is then stored as the offset
+pub const ABSOLUTE_BASE: u8 = 15;
+
+/// Version of the binary format, written at the beginning of the header. The changes brought by each version are listed in the README.
+pub const FORMAT_VERSION: u32 = 2;
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum OpCode {
     ADD,
@@ -46,129 +108,75 @@ pub enum OpCode {
     TRACE,
     DUP,
     SWAP,
+    LOAD(WIDTH),
+    STORE(WIDTH),
+    CMOV(JCODE),
+    SET(JCODE),
+    DROP,
+    OVER,
+    ROT,
+    PICK,
+    ROL,
+    ROR,
+    SAR,
+    DIVU,
+    MODU,
+    MULH,
+    MULHU,
+    POPCNT,
+    CLZ,
 }
 
 impl TryFrom<&str> for OpCode {
     type Error = SyntaxErrorKind;
 
     fn try_from(s: &str) -> Result<Self, Self::Error> {
-        Ok(match s {
-            "add" => OpCode::ADD,
-            "mul" => OpCode::MUL,
-            "sub" => OpCode::SUB,
-            "div" => OpCode::DIV,
-            "mod" => OpCode::MOD,
-            "neg" => OpCode::NEG,
-            "shl" => OpCode::SHL,
-            "shr" => OpCode::SHR,
-            "and" => OpCode::AND,
-            "or" => OpCode::OR,
-            "xor" => OpCode::XOR,
-            "not" => OpCode::NOT,
-            "jmp" => OpCode::JMP(JCODE::JMP),
-            "je" => OpCode::JMP(JCODE::JE),
-            "jl" => OpCode::JMP(JCODE::JL),
-            "jh" => OpCode::JMP(JCODE::JH),
-            "jhe" => OpCode::JMP(JCODE::JHE),
-            "jle" => OpCode::JMP(JCODE::JLE),
-            "int" => OpCode::INT,
-            "push" => OpCode::PUSH,
-            "pop" => OpCode::POP,
-            "ret" => OpCode::RET,
-            "call" => OpCode::CALL,
-            "mov" => OpCode::MOV,
-            "rd" => OpCode::READ,
-            "wr" => OpCode::WRITE,
-            "cmp" => OpCode::CMP,
-            "clear" => OpCode::CLEAR,
-            "halt" => OpCode::HALT,
-            "trace" => OpCode::TRACE,
-            "dup" => OpCode::DUP,
-            "swap" => OpCode::SWAP,
-            _ => return Err(SyntaxErrorKind::InvalidOpCode(s.to_string())),
-        })
+        ISA.iter()
+            .flat_map(|i| i.mnemonics)
+            .find(|(mnemonic, _)| *mnemonic == s)
+            .map(|(_, op_code)| *op_code)
+            .ok_or_else(|| SyntaxErrorKind::InvalidOpCode(s.to_string()))
     }
 }
 
 impl OpCode {
-    pub fn check_compatibility(self, line: &[Word]) -> SyntaxResultKind<()> {
+    /// Returns the instruction of the ISA table the op code belongs to
+    pub fn instruction(self) -> &'static Instruction {
+        ISA.iter()
+            .find(|i| i.mnemonics.iter().any(|(_, op_code)| *op_code == self))
+            .unwrap()
+    }
+
+    /// Returns the condition or the width carried by the op code, stored in the instruction with it
+    pub fn parameter(self) -> Option<u8> {
         match self {
-            Self::MOD
-            | Self::ADD
-            | Self::MUL
-            | Self::SUB
-            | Self::DIV
-            | Self::SHL
-            | Self::SHR
-            | Self::AND
-            | Self::OR
-            | Self::XOR
-            | Self::NOT
-            | Self::CMP
-            | Self::READ
-            | Self::WRITE
-            | Self::MOV
-                if line.len() != 2 || !line[0].is_reg() || !line[1].is_reg_or_imm() =>
-            {
-                Err(SyntaxErrorKind::ExpectedRegImmOrReg(self))
-            }
-
-            Self::HALT
-            | Self::SWAP
-            | Self::DUP
-            | Self::CLEAR
-            | Self::TRACE
-            | Self::NEG
-            | Self::RET
-                if line.len() != 0 =>
-            {
-                Err(SyntaxErrorKind::ExpectedNothing(self))
-            } // Nothing
-
-            Self::INT | Self::JMP(_) | Self::CALL | Self::PUSH
-                if line.len() != 1 || !line[0].is_reg_or_imm() =>
-            {
-                Err(SyntaxErrorKind::ExpectedRegOrImm(self))
-            } // reg || imm
-
-            Self::POP if line.len() != 1 || !line[0].is_reg() => {
-                Err(SyntaxErrorKind::ExpectedReg(self))
-            } // reg
-            _ => Ok(()),
+            Self::JMP(jcode) | Self::CMOV(jcode) | Self::SET(jcode) => Some(jcode.into()),
+            Self::LOAD(width) | Self::STORE(width) => Some(width.into()),
+            _ => None,
         }
     }
-}
 
-impl From<OpCode> for u8 {
-    fn from(op: OpCode) -> u8 {
-        match op {
-            OpCode::ADD => 0,
-            OpCode::MUL => 1,
-            OpCode::SUB => 2,
-            OpCode::DIV => 3,
-            OpCode::MOD => 4,
-            OpCode::NEG => 5,
-            OpCode::SHL => 6,
-            OpCode::SHR => 7,
-            OpCode::AND => 8,
-            OpCode::OR => 9,
-            OpCode::XOR => 10,
-            OpCode::NOT => 11,
-            OpCode::JMP(_) => 12,
-            OpCode::INT => 13,
-            OpCode::PUSH => 14,
-            OpCode::POP => 15,
-            OpCode::RET => 16,
-            OpCode::CALL => 17,
-            OpCode::MOV => 18,
-            OpCode::READ => 19,
-            OpCode::WRITE => 20,
-            OpCode::CMP => 21,
-            OpCode::CLEAR => 22,
-            OpCode::HALT => 23,
-            OpCode::TRACE => 24,
-            OpCode::DUP => 25,
-            OpCode::SWAP => 26,
+    /// Returns the form of the instruction matching the operands, the operands are checked by check_compatibility before
+    pub fn form(self, line: &[&Word]) -> &'static Form {
+        self.instruction()
+            .forms
+            .iter()
+            .find(|f| f.accepts(line))
+            .unwrap()
+    }
+
+    pub fn check_compatibility(self, line: &[Word]) -> SyntaxResultKind<()> {
+        let line = line.iter().collect::<Vec<_>>();
+        let forms = self.instruction().forms;
+        match forms.iter().find(|f| f.accepts(&line)) {
+            Some(form) => form.check_destinations(&line),
+            // The error describes the form with as many operands if any, else the first one taking operands, the shorthands are not described
+            None => Err(forms
+                .iter()
+                .find(|f| f.operands.len() == line.len() && !f.layout.is_shorthand())
+                .or_else(|| forms.iter().find(|f| !f.operands.is_empty()))
+                .unwrap_or(&forms[0])
+                .expected(self)),
         }
     }
 }
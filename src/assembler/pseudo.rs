@@ -0,0 +1,85 @@
+use super::{
+    errors::{SyntaxErrorKind, SyntaxResultKind},
+    macro_def::split_arguments,
+    registers::Register,
+};
+
+/// Each pseudo-instruction with the operands it expects
+const USAGES: [(&str, &str); 9] = [
+    ("nop", "nop"),
+    ("inc", "inc reg"),
+    ("dec", "dec reg"),
+    ("li", "li reg, imm"),
+    ("jz", "jz reg, target"),
+    ("jnz", "jnz reg, target"),
+    ("push", "push reg|imm, reg|imm, ..."),
+    ("pop", "pop reg, reg, ..."),
+    ("tail", "tail target"),
+];
+
+/// Returns the lines of real instructions replacing a pseudo-instruction, None if the name is not a pseudo-instruction
+pub fn expand(name: &str, operands: &str) -> Option<SyntaxResultKind<Vec<String>>> {
+    let usage = USAGES.iter().find(|(pseudo, _)| *pseudo == name)?.1;
+    let ops = split_arguments(operands);
+    Some(match (name, ops.as_slice()) {
+        ("nop", []) => Ok(vec![String::from("mov r0, r0")]),
+        ("inc", [reg]) => Ok(vec![format!("add {reg}, 1")]),
+        ("dec", [reg]) => Ok(vec![format!("sub {reg}, 1")]),
+        // The immediate value of mov is too short for a whole word, the value is loaded by halves and always takes 3 instructions
+        ("li", [reg, value]) if Register::try_from(value.as_str()).is_err() => Ok(vec![
+            format!("mov {reg}, ({value}) >> 16 & 0xFFFF"),
+            format!("shl {reg}, 16"),
+            format!("or {reg}, ({value}) & 0xFFFF"),
+        ]),
+        ("jz", [reg, target]) => Ok(vec![format!("cmp {reg}, 0"), format!("je {target}")]),
+        ("jnz", [reg, target]) => Ok(vec![format!("cmp {reg}, 0"), format!("jne {target}")]),
+        // With a single operand, push and pop are real instructions
+        ("push" | "pop", [_]) => return None,
+        ("push" | "pop", [_, _, ..]) => Ok(ops.iter().map(|op| format!("{name} {op}")).collect()),
+        ("tail", [target]) => Ok(vec![format!("jmp {target}")]),
+        _ => Err(SyntaxErrorKind::InvalidPseudoOperands(usage)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn other_names_are_not_pseudo_instructions() {
+        assert!(expand("mov", "r0, r1").is_none());
+        // With a single operand, push and pop are real instructions
+        assert!(expand("push", "r0").is_none());
+        assert!(expand("pop", "r0").is_none());
+    }
+
+    #[test]
+    fn push_accepts_immediate_values() {
+        assert_eq!(
+            expand("push", "r0, 5, LABEL + 1").unwrap().unwrap(),
+            ["push r0", "push 5", "push LABEL + 1"]
+        );
+    }
+
+    #[test]
+    fn li_always_takes_three_instructions() {
+        assert_eq!(
+            expand("li", "r0, label").unwrap().unwrap(),
+            [
+                "mov r0, (label) >> 16 & 0xFFFF",
+                "shl r0, 16",
+                "or r0, (label) & 0xFFFF"
+            ]
+        );
+    }
+
+    #[test]
+    fn wrong_operands_give_the_usage() {
+        for (name, operands) in [("li", "r0, r1"), ("li", "r0"), ("inc", ""), ("nop", "r0")] {
+            assert!(matches!(
+                expand(name, operands),
+                Some(Err(SyntaxErrorKind::InvalidPseudoOperands(_)))
+            ));
+        }
+    }
+}
@@ -19,22 +19,26 @@ as_number!(
     derive(Clone, Copy, Debug)
 );
 
+impl Register {
+    /// The condition register is only written by the comparisons
+    pub fn is_writable(self) -> bool {
+        !matches!(self, Self::COND)
+    }
+}
+
+/// The names of the registers, in the order of their numbers
+pub const NAMES: [&str; 10] = [
+    "r0", "r1", "r2", "r3", "r4", "r5", "r6", "r7", "rpc", "rcond",
+];
+
 impl TryFrom<&str> for Register {
     type Error = SyntaxErrorKind;
 
     fn try_from(s: &str) -> Result<Self, Self::Error> {
-        Ok(match s {
-            "r0" => Register::R0,
-            "r1" => Register::R1,
-            "r2" => Register::R2,
-            "r3" => Register::R3,
-            "r4" => Register::R4,
-            "r5" => Register::R5,
-            "r6" => Register::R6,
-            "r7" => Register::R7,
-            "rpc" => Register::PC,
-            "rcond" => Register::COND,
-            _ => return Err(SyntaxErrorKind::InvalidRegister(s.to_string())),
-        })
+        NAMES
+            .iter()
+            .position(|name| *name == s)
+            .map(|n| Register::from(n as u8))
+            .ok_or_else(|| SyntaxErrorKind::InvalidRegister(s.to_string()))
     }
 }
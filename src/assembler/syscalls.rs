@@ -0,0 +1,54 @@
+/// An interrupt handled by the ZVM, its name is declared as a constant holding its number
+pub struct Syscall {
+    pub name: &'static str,
+    pub number: i32,
+    pub description: &'static str,
+}
+
+/// The interrupts of the ZVM, given as operand to the INT instruction, exemple: int SYS_WRITE
+pub const SYSCALLS: [Syscall; 5] = [
+    Syscall {
+        name: "SYS_READ",
+        number: 0,
+        description: "Read a line of the standard input in the buffer at the address held by r0",
+    },
+    Syscall {
+        name: "SYS_WRITE",
+        number: 1,
+        description:
+            "Write the null terminated string at the address held by r0 on the standard output",
+    },
+    Syscall {
+        name: "SYS_EXIT",
+        number: 2,
+        description: "Stop the program, the exit code is held by r0",
+    },
+    Syscall {
+        name: "SYS_ALLOC",
+        number: 3,
+        description:
+            "Allocate the number of bytes held by r0, the address of the block is returned in r0",
+    },
+    Syscall {
+        name: "SYS_FREE",
+        number: 4,
+        description: "Free the block allocated at the address held by r0",
+    },
+];
+
+/// Returns the interrupt of the given number if the ZVM handles it
+pub fn find_syscall(number: i64) -> Option<&'static Syscall> {
+    SYSCALLS.iter().find(|s| s.number as i64 == number)
+}
+
+/// Returns true if the name is the one of an interrupt, so it can't be declared in the source
+pub fn is_syscall_name(name: &str) -> bool {
+    SYSCALLS.iter().any(|s| s.name == name)
+}
+
+/// Print the table of the interrupts, as given by the option --help-syscalls
+pub fn print_syscalls() {
+    for s in &SYSCALLS {
+        println!("{:>4}  {:<10} {}", s.number, s.name, s.description);
+    }
+}
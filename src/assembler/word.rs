@@ -1,5 +1,7 @@
 use super::{
+    directives::{Directive, Section},
     errors::{is_valid_label_name, SyntaxErrorKind, SyntaxResultKind},
+    expr::{Expr, OPERATOR_CHARS},
     op_codes::OpCode,
     registers::Register,
     COMMENT_CHAR,
@@ -71,8 +73,91 @@ pub enum WordContent {
     OpCode(OpCode),
     /// Will represent a valid Register
     Register(Register),
-    /// Represent a litteral string (between double quotes)
+    /// Will represent a valid Directive, such as .byte
+    Directive(Directive),
+    /// Will represent a valid section name, such as .data
+    Section(Section),
+    /// Represent a litteral string (between double quotes), the null terminator is not included
     Str(String),
+    /// A word containing operators, it is only a part of an expression that will be rebuilt with the other words of the operand, exemple: "(BUF_SIZE"
+    ExprPart(String),
+    /// An expression that can't be computed before knowing the address of the labels it uses
+    Expr(Expr),
+    /// A memory operand between brackets, the address is the base register if any plus the offset, exemple: "[r1 + 8]" or "[table]"
+    Memory(Option<Register>, Box<Word>),
+}
+
+/// Cast a computed value into a number, values between i32::MAX and u32::MAX are stored with the same bits
+pub fn to_number(value: i64) -> SyntaxResultKind<i32> {
+    if value < i32::MIN as i64 || value > u32::MAX as i64 {
+        Err(SyntaxErrorKind::ValueOutOfRange(value))
+    } else {
+        Ok(value as u32 as i32)
+    }
+}
+
+/// Parse a number, eventually negative, written in decimal or with one of the prefixes 0x, 0o or 0b. Values between i32::MAX and u32::MAX are accepted and stored with the same bits.
+pub fn parse_number(s: &str) -> Option<i32> {
+    let (neg, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s),
+    };
+    let (radix, digits) = match digits.get(..2) {
+        Some("0x") | Some("0X") => (16, &digits[2..]),
+        Some("0o") | Some("0O") => (8, &digits[2..]),
+        Some("0b") | Some("0B") => (2, &digits[2..]),
+        _ => (10, digits),
+    };
+    if digits.is_empty() || digits.starts_with(['-', '+']) {
+        return None;
+    }
+    let value = i64::from_str_radix(digits, radix).ok()?;
+    to_number(if neg { -value } else { value }).ok()
+}
+
+/// The brackets of a memory operand are kept in the parts, the operand is rebuilt as an expression is
+fn is_expr_part(s: &str) -> bool {
+    s.contains(|c| OPERATOR_CHARS.contains(c) || c == '[' || c == ']')
+}
+
+/// Parse a memory operand, exemple: "[r1 + 8]", "[r2 - 4]" or "[table + 4]". Without base register, the offset is the absolute address.
+pub fn parse_memory(text: &str) -> SyntaxResultKind<WordContent> {
+    let invalid = || SyntaxErrorKind::InvalidMemoryOperand(text.to_string());
+    let inner = text
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .ok_or_else(invalid)?
+        .trim();
+    let end = inner
+        .find(|c: char| !c.is_alphanumeric() && !"_.@".contains(c))
+        .unwrap_or(inner.len());
+    let (base, offset) = match Register::try_from(&inner[..end]) {
+        Ok(reg) => {
+            let rest = inner[end..].trim();
+            match rest.strip_prefix('+') {
+                Some(offset) => (Some(reg), offset.trim()),
+                None if rest.is_empty() => (Some(reg), "0"),
+                None if rest.starts_with('-') => (Some(reg), rest),
+                None => return Err(invalid()),
+            }
+        }
+        Err(_) => (None, inner),
+    };
+    let content = if let Some(x) = parse_number(offset) {
+        WordContent::Number(x)
+    } else if is_valid_label_name(offset) {
+        WordContent::Label(offset.to_string())
+    } else {
+        WordContent::Expr(Expr::try_from(offset).map_err(|_| invalid())?)
+    };
+    Ok(WordContent::Memory(
+        base,
+        Box::new(Word::new(
+            content,
+            offset.to_string(),
+            WordSeparator::default(),
+        )),
+    ))
 }
 
 /// If s is a quote, it will replace the backslash character by its real value. It can fail if there is an invalid backslash character, but as this case is checks before, it may not.
@@ -125,12 +210,18 @@ impl WordContent {
                     WordContent::Register(reg)
                 } else if let Ok(opcode) = OpCode::try_from(&pure_content as &str) {
                     WordContent::OpCode(opcode)
+                } else if let Ok(directive) = Directive::try_from(&pure_content as &str) {
+                    WordContent::Directive(directive)
+                } else if let Ok(section) = Section::try_from(&pure_content as &str) {
+                    WordContent::Section(section)
+                } else if let Some(x) = parse_number(&pure_content) {
+                    WordContent::Number(x)
+                } else if is_valid_label_name(&pure_content) {
+                    WordContent::Label(pure_content.to_string())
+                } else if is_expr_part(&pure_content) {
+                    WordContent::ExprPart(pure_content)
                 } else {
-                    if is_valid_label_name(&pure_content) {
-                        WordContent::Label(pure_content.to_string())
-                    } else {
-                        return Err(SyntaxErrorKind::InvalidWord(pure_content));
-                    }
+                    return Err(SyntaxErrorKind::InvalidWord(pure_content));
                 }
             }
             WordKind::LabelDeclaration => {
@@ -141,14 +232,13 @@ impl WordContent {
                     return Err(SyntaxErrorKind::InvalidLabelName(pure_content));
                 }
             }
-            WordKind::Number => WordContent::Number(match pure_content.parse::<i32>() {
-                Ok(x) => x,
-                Err(_) => return Err(SyntaxErrorKind::InvalidNumber(pure_content)),
-            }),
+            WordKind::Number => match parse_number(&pure_content) {
+                Some(x) => WordContent::Number(x),
+                None if is_expr_part(&pure_content) => WordContent::ExprPart(pure_content),
+                None => return Err(SyntaxErrorKind::InvalidNumber(pure_content)),
+            },
             WordKind::DoubleQuote => {
-                let mut content = pure_content[1..pure_content.len() - 1].to_string();
-                content.push('\0');
-                WordContent::Str(content)
+                WordContent::Str(pure_content[1..pure_content.len() - 1].to_string())
             }
             WordKind::SingleQuote => {
                 WordContent::Number(Self::extract_number_from_single_quote(&pure_content)?)
@@ -168,7 +258,10 @@ pub struct Word {
     sep: WordSeparator,
 }
 
-use std::fmt::{Debug, Error as FmtErr, Formatter};
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Error as FmtErr, Formatter},
+};
 
 impl Debug for Word {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::result::Result<(), FmtErr> {
@@ -196,7 +289,21 @@ impl Word {
         }
     }
 
-    pub fn get_str<'a>(&'a self) -> Option<&'a String> {
+    pub fn get_directive(&self) -> Option<Directive> {
+        match self.content {
+            WordContent::Directive(directive) => Some(directive),
+            _ => None,
+        }
+    }
+
+    pub fn get_section(&self) -> Option<Section> {
+        match self.content {
+            WordContent::Section(section) => Some(section),
+            _ => None,
+        }
+    }
+
+    pub fn get_str(&self) -> Option<&String> {
         match &self.content {
             WordContent::Str(s) => Some(s),
             _ => None,
@@ -210,33 +317,108 @@ impl Word {
         }
     }
 
-    pub fn is_label_decl(&self) -> bool {
-        match self.content {
-            WordContent::LabelDeclaration(_) => true,
-            _ => false,
+    /// Returns the base register and the offset of a memory operand
+    pub fn get_memory(&self) -> Option<(Option<Register>, &Word)> {
+        match &self.content {
+            WordContent::Memory(base, offset) => Some((*base, offset)),
+            _ => None,
         }
     }
 
+    pub fn sep(&self) -> WordSeparator {
+        self.sep
+    }
+
+    /// Returns true if the word has been ended by a comma, meaning that an other operand is expected after it
+    pub fn ends_with_comma(&self) -> bool {
+        self.sep == WordSeparator::Comma
+    }
+
+    pub fn is_label_decl(&self) -> bool {
+        matches!(self.content, WordContent::LabelDeclaration(_))
+    }
+
     pub fn is_reg(&self) -> bool {
-        match self.content {
-            WordContent::Register(_) => true,
-            _ => false,
+        matches!(self.content, WordContent::Register(_))
+    }
+
+    pub fn is_imm(&self) -> bool {
+        matches!(
+            self.content,
+            WordContent::Number(_) | WordContent::Label(_) | WordContent::Expr(_)
+        )
+    }
+
+    /// Returns the text of the word as a part of an expression, if the word can be used in an expression
+    pub fn get_expr_part(&self) -> Option<String> {
+        match &self.content {
+            WordContent::Number(x) => Some(x.to_string()),
+            WordContent::Label(s) | WordContent::ExprPart(s) => Some(s.to_string()),
+            _ => None,
         }
     }
 
-    pub fn is_reg_or_imm(&self) -> bool {
-        match self.content {
-            WordContent::Number(_) | WordContent::Label(_) | WordContent::Register(_) => true,
-            _ => false,
+    /// Replace the constants known by the resolver by their value, the word becomes a number if nothing is left to resolve
+    pub fn fold_constants(
+        &mut self,
+        resolve: &impl Fn(&str) -> Option<i64>,
+    ) -> SyntaxResultKind<()> {
+        let value = match &mut self.content {
+            WordContent::Label(lab) => resolve(lab),
+            WordContent::Expr(expr) => {
+                expr.substitute(resolve);
+                if expr.symbols().is_empty() {
+                    Some(expr.eval(resolve)?)
+                } else {
+                    None
+                }
+            }
+            WordContent::Memory(_, offset) => {
+                offset.fold_constants(resolve)?;
+                None
+            }
+            _ => None,
+        };
+        if let Some(x) = value {
+            self.content = WordContent::Number(to_number(x)?)
         }
+        Ok(())
     }
 
-    pub fn is_str(&self) -> bool {
-        match self.content {
-            WordContent::Str(_) => true,
-            _ => false,
+    /// Returns the value of an immediate word, labels are replaced by their address
+    pub fn get_value(&self, labels: &HashMap<String, u64>) -> SyntaxResultKind<i64> {
+        let resolve = |lab: &str| labels.get(lab).map(|addr| *addr as i64);
+        match &self.content {
+            WordContent::Number(x) => Ok(*x as i64),
+            WordContent::Label(lab) => Expr::Symbol(lab.to_string()).eval(&resolve),
+            WordContent::Expr(expr) => expr.eval(&resolve),
+            _ => unreachable!(),
         }
     }
+
+    /// Returns the base register and the offset of an address, a register alone is a base without offset and an immediate value is an absolute address
+    pub fn get_address(
+        &self,
+        labels: &HashMap<String, u64>,
+    ) -> SyntaxResultKind<(Option<Register>, i64)> {
+        Ok(match &self.content {
+            WordContent::Memory(base, offset) => (*base, offset.get_value(labels)?),
+            WordContent::Register(reg) => (Some(*reg), 0),
+            _ => (None, self.get_value(labels)?),
+        })
+    }
+
+    pub fn is_reg_or_imm(&self) -> bool {
+        self.is_reg() || self.is_imm()
+    }
+
+    pub fn is_memory(&self) -> bool {
+        matches!(self.content, WordContent::Memory(..))
+    }
+
+    pub fn is_str(&self) -> bool {
+        matches!(self.content, WordContent::Str(_))
+    }
 }
 
 pub enum WordRequest {
@@ -289,8 +471,7 @@ impl WordKind {
                 WordSeparator::Space,
                 WordSeparator::SingleQuote,
                 WordSeparator::DoubleQuote,
-                WordSeparator::EndOfLine,
-                WordSeparator::Others, // NOTE: A number is only allow to read digit
+                WordSeparator::EndOfLine, // NOTE: Letters are read to support prefixes such as 0x, the validity is checked when parsing
             ],
             WordKind::DoubleQuote => vec![WordSeparator::DoubleQuote],
             WordKind::SingleQuote => vec![WordSeparator::SingleQuote], // As quotes are a bit special, we have to assure before ending the computation that the character was not just after a backslash
@@ -306,7 +487,7 @@ impl TryFrom<char> for WordKind {
             '\'' => Self::SingleQuote,
             '\"' => Self::DoubleQuote,
             _ if c.is_ascii_digit() => Self::Number,
-            _ if c.is_alphabetic() || ", :\n\0".contains(c) => Self::Unknown,
+            _ if c.is_alphabetic() || ", :\n".contains(c) => Self::Unknown,
             _ => return Err(SyntaxErrorKind::InvalidFirstChar(c)),
         })
     }
@@ -321,10 +502,11 @@ pub struct WordBuilder {
 }
 
 impl WordBuilder {
+    /// The builder starts as if a line had just ended, so the first line is read as the other ones
     pub fn new() -> SyntaxResultKind<Self> {
         Ok(Self {
-            pure_content: String::from('\0'),
-            kind: WordKind::try_from('\0')?,
+            pure_content: String::from('\n'),
+            kind: WordKind::try_from('\n')?,
         })
     }
 
@@ -338,9 +520,9 @@ impl WordBuilder {
         Ok(())
     }
 
-    pub fn end_of_file(&mut self) -> SyntaxResultKind<Word> {
-        self.kind.check_valid_ending_word()?;
-        self.extract(WordSeparator::EndOfLine, '\0')
+    /// Checks that the line can end here, a quote can't be continued on the next line
+    pub fn check_end_of_line(&self) -> SyntaxResultKind<()> {
+        self.kind.check_valid_ending_word()
     }
 
     /// This funtion extact the built word and clean the builder itself
@@ -374,7 +556,7 @@ impl WordBuilder {
 
     fn previous_was_backslash(&self) -> bool {
         let mut chars = self.pure_content.chars().rev();
-        self.pure_content.len() != 0
+        !self.pure_content.is_empty()
             && chars.next().unwrap() == '\\'
             && (self.pure_content.len() == 1 || chars.next().unwrap() != '\\')
     }
@@ -399,12 +581,10 @@ impl WordBuilder {
         &mut self,
         chars: &mut impl Iterator<Item = char>,
     ) -> SyntaxResultKind<WordRequest> {
-        while let Some(c) = chars.next() {
-            if c == '\n' {
-                return self.add_char(c, chars);
-            }
+        match chars.find(|c| *c == '\n') {
+            Some(c) => self.add_char(c, chars),
+            None => Ok(WordRequest::Continue), // This append only if we reach EOF
         }
-        Ok(WordRequest::Continue) // This append only if we reach EOF
     }
 
     pub fn add_char(
@@ -1,23 +1,151 @@
+#![allow(clippy::upper_case_acronyms)]
 mod assembler;
 mod macros;
-use std::{env::args, fs::File, io::Read, process::exit};
+use std::{env::args, fs, path::Path, process::exit};
 
-use assembler::assemble;
+use assembler::{
+    assemble, is_valid_label_name, print_disassembly, print_isa, print_syscalls, Config,
+};
+
+/// Exit code of an invalid command line, the codes under it are the ones of the syntax errors
+const USAGE_ERROR: i32 = 100;
+/// Exit code when the binary can't be written in the output file
+const OUTPUT_ERROR: i32 = 101;
+
+/// Print the problem found in the command line and exit
+fn usage_error(message: &str) -> ! {
+    println!("Usage error: {message}");
+    exit(USAGE_ERROR)
+}
+
+/// Parse an address written in decimal or in hexadecimal with the prefix 0x
+fn parse_address(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Parse a definition given with -D, NAME=value or NAME alone for the value 1
+fn parse_define(s: &str) -> Result<(String, i32), String> {
+    let (name, value) = match s.split_once('=') {
+        Some((name, value)) => (
+            name,
+            value
+                .parse()
+                .ok()
+                .or_else(|| parse_address(value).and_then(|x| i32::try_from(x).ok()))
+                .ok_or_else(|| {
+                    format!("Expected a number on 32 bits as value of -D, found \"{value}\"")
+                })?,
+        ),
+        None => (s, 1),
+    };
+    if !is_valid_label_name(name) {
+        return Err(format!(
+            "Expected a constant name with -D, found \"{name}\""
+        ));
+    }
+    Ok((name.to_string(), value))
+}
 
 fn main() {
-    let mut f = File::open(args().nth(1).unwrap_or(String::from("./exemples/test.nzm"))).unwrap();
-    let mut content = String::new();
-    f.read_to_string(&mut content).unwrap();
-    assemble(
-        File::options()
-            .write(true)
-            .create(true)
-            .open(args().nth(2).unwrap_or(String::from("a.out")))
-            .expect("Failed to create a.out file"),
-        &content,
-    )
-    .unwrap_or_else(|e| {
+    let mut config = Config::default();
+    let mut paths = Vec::new();
+    let mut disassemble = None;
+    let mut args = args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--auto-align" => config.auto_align = true,
+            "--listing" => config.listing = true,
+            "--help-syscalls" => {
+                print_syscalls();
+                return;
+            }
+            "--help-isa" => {
+                print_isa();
+                return;
+            }
+            "--disassemble" => {
+                disassemble =
+                    Some(args.next().unwrap_or_else(|| {
+                        usage_error("Expected a binary file after --disassemble")
+                    }))
+            }
+            "-I" => config.include_paths.push(
+                args.next()
+                    .unwrap_or_else(|| usage_error("Expected a directory after -I"))
+                    .into(),
+            ),
+            "--base" => {
+                config.base = args
+                    .next()
+                    .as_deref()
+                    .and_then(parse_address)
+                    .unwrap_or_else(|| usage_error("Expected an address after --base"))
+            }
+            "--entry" => {
+                config.entry = Some(
+                    args.next()
+                        .unwrap_or_else(|| usage_error("Expected a label after --entry")),
+                )
+            }
+            "-D" => config.defines.push(
+                parse_define(
+                    &args
+                        .next()
+                        .unwrap_or_else(|| usage_error("Expected a definition after -D")),
+                )
+                .unwrap_or_else(|e| usage_error(&e)),
+            ),
+            _ if arg.starts_with("-D") => config
+                .defines
+                .push(parse_define(&arg[2..]).unwrap_or_else(|e| usage_error(&e))),
+            _ if arg.starts_with("-I") => config.include_paths.push(arg[2..].into()),
+            _ if arg.starts_with('-') => usage_error(&format!("Unknown option {arg}")),
+            _ if paths.len() == 2 => usage_error(&format!(
+                "Expected a source file and an output file, found the other path \"{arg}\""
+            )),
+            _ => paths.push(arg),
+        }
+    }
+    if let Some(path) = disassemble {
+        print_disassembly(Path::new(&path), config.base);
+        return;
+    }
+    let mut paths = paths.into_iter();
+
+    let input = paths.next().unwrap_or(String::from("./exemples/test.nzm"));
+    let output = paths.next().unwrap_or(String::from("a.out"));
+    let binary = assemble(Path::new(&input), config).unwrap_or_else(|e| {
         println!("{e:?}");
         exit(From::from(&e));
     });
+    // The output is only created once the assembly succeeded
+    if let Err(e) = fs::write(&output, binary) {
+        println!("Failed to write the output file {output}: {e}");
+        exit(OUTPUT_ERROR)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defines_are_parsed() {
+        assert_eq!(parse_define("DEBUG"), Ok((String::from("DEBUG"), 1)));
+        assert_eq!(parse_define("LEVEL=-3"), Ok((String::from("LEVEL"), -3)));
+        assert_eq!(parse_define("BASE=0x10"), Ok((String::from("BASE"), 16)));
+        for s in [
+            "FOO=abc",
+            "=1",
+            "FOO=",
+            "A+B=2",
+            "X=0x1ffffffff",
+            "X=4294967295",
+        ] {
+            assert!(parse_define(s).is_err(), "{s}");
+        }
+    }
 }